@@ -0,0 +1,144 @@
+//! Opcodes and instruction encoders mirroring the decoding in the VM's `execute_instr`.
+//!
+//! The opcode always occupies bits 48..64. Register operands go into the byte fields at bits 40..48, 32..40
+//! and 24..32 in the order the instruction reads them. This is the only opcode table: the VM's `Opcode` enum and
+//! its encoders are built on top of it.
+
+pub const NOP: u64 = 0x0000;
+pub const MOVE: u64 = 0xCD00;
+pub const SET: u64 = 0xCD01;
+pub const ADD: u64 = 0xCD02;
+pub const SUB: u64 = 0xCD03;
+pub const MUL: u64 = 0xCD04;
+pub const DIV: u64 = 0xCD05;
+pub const LOAD_ABSOLUTE: u64 = 0xCD06;
+pub const STORE_ABSOLUTE: u64 = 0xCD07;
+pub const LOAD: u64 = 0xCD08;
+pub const STORE: u64 = 0xCD09;
+pub const CMP: u64 = 0xCD0A;
+pub const MEMCPY: u64 = 0xCD0B;
+pub const SWAP: u64 = 0xCD0C;
+pub const SDIV: u64 = 0xCD0D;
+pub const SREM: u64 = 0xCD0E;
+pub const INC: u64 = 0xCD0F;
+pub const DEC: u64 = 0xCD10;
+pub const ADDC: u64 = 0xCD11;
+pub const MULC: u64 = 0xCD12;
+pub const LEA: u64 = 0xCD13;
+pub const CMOV: u64 = 0xCD14;
+pub const CAS: u64 = 0xCD15;
+pub const ADDM: u64 = 0xCD16;
+pub const SUBM: u64 = 0xCD17;
+pub const ADC: u64 = 0xCD18;
+pub const NEG: u64 = 0xCD19;
+pub const ABS: u64 = 0xCD1A;
+pub const FADD: u64 = 0xCB00;
+pub const FSUB: u64 = 0xCB01;
+pub const FMUL: u64 = 0xCB02;
+pub const FDIV: u64 = 0xCB03;
+pub const ITOF: u64 = 0xCB04;
+pub const FTOI: u64 = 0xCB05;
+pub const AND: u64 = 0xCE00;
+pub const OR: u64 = 0xCE01;
+pub const XOR: u64 = 0xCE02;
+pub const NOT: u64 = 0xCE03;
+pub const SHL: u64 = 0xCE04;
+pub const SHR: u64 = 0xCE05;
+pub const SAR: u64 = 0xCE06;
+pub const POPCNT: u64 = 0xCE07;
+pub const CLZ: u64 = 0xCE08;
+pub const CTZ: u64 = 0xCE09;
+pub const BSWAP: u64 = 0xCE0A;
+pub const PUSH: u64 = 0xCC00;
+pub const POP: u64 = 0xCC01;
+pub const JMP: u64 = 0xCF00;
+pub const JMC: u64 = 0xCF01;
+pub const CALL: u64 = 0xCF02;
+pub const RET: u64 = 0xCF03;
+pub const JZ: u64 = 0xCF04;
+pub const JNZ: u64 = 0xCF05;
+pub const JS: u64 = 0xCF06;
+pub const JNS: u64 = 0xCF07;
+pub const JC: u64 = 0xCF08;
+pub const JNC: u64 = 0xCF09;
+pub const JO: u64 = 0xCF0A;
+pub const JNO: u64 = 0xCF0B;
+pub const JMCI: u64 = 0xCF0C;
+pub const CALLR: u64 = 0xCF0D;
+pub const FUEL: u64 = 0xFFF0;
+pub const SYSCALL: u64 = 0xFFF1;
+pub const EXIT: u64 = 0xFFFA;
+pub const HALT: u64 = 0xFFFF;
+
+/// The largest immediate `$set` can hold in its 40-bit field.
+pub const SET_MAX: u64 = (1 << 40) - 1;
+
+/// Encodes an instruction without operands, e.g. `$nop`, `$ret` or `$halt`.
+pub fn bare(opcode: u64) -> u64 {
+    opcode << 48
+}
+/// Encodes an instruction with one register (or small constant) operand, e.g. `$push` or `$exit`.
+pub fn unary(opcode: u64, a: usize) -> u64 {
+    bare(opcode) | ((a as u64 & 0xFF) << 40)
+}
+/// Encodes an instruction with two register operands, e.g. `$move` or `$cmp`.
+pub fn binary(opcode: u64, a: usize, b: usize) -> u64 {
+    unary(opcode, a) | ((b as u64 & 0xFF) << 32)
+}
+/// Encodes an instruction with three register operands, e.g. `$add`.
+pub fn ternary(opcode: u64, a: usize, b: usize, c: usize) -> u64 {
+    binary(opcode, a, b) | ((c as u64 & 0xFF) << 24)
+}
+/// Encodes an instruction with four register operands, the last one in bits 16..24, e.g. `$cas`.
+pub fn quaternary(opcode: u64, a: usize, b: usize, c: usize, d: usize) -> u64 {
+    ternary(opcode, a, b, c) | ((d as u64 & 0xFF) << 16)
+}
+/// Encodes `$set` with an immediate of at most [SET_MAX].
+pub fn set(reg: usize, imm: u64) -> u64 {
+    unary(SET, reg) | (imm & SET_MAX)
+}
+/// Encodes `$lea` with an address of at most [SET_MAX].
+pub fn lea(reg: usize, address: u64) -> u64 {
+    unary(LEA, reg) | (address & SET_MAX)
+}
+/// Encodes a jump or call whose target address occupies bits 0..48, or a `$syscall` with its number there.
+pub fn jump(opcode: u64, address: u64) -> u64 {
+    bare(opcode) | (address & 0xFFFF_FFFF_FFFF)
+}
+/// Encodes a memory access of `size` bits at a 32-bit absolute address.
+pub fn absolute(opcode: u64, reg: usize, size: usize, address: u64) -> u64 {
+    binary(opcode, reg, size) | (address & 0xFFFF_FFFF)
+}
+/// Encodes a memory access of `size` bits at `base` plus a 24-bit displacement, e.g. `$addm`.
+pub fn memory(opcode: u64, reg: usize, size: usize, base: usize, disp: u64) -> u64 {
+    ternary(opcode, reg, size, base) | (disp & 0xFF_FFFF)
+}
+/// Encodes a load of `size` bits from `base` plus a 24-bit displacement into `reg`.
+pub fn load(reg: usize, size: usize, base: usize, disp: u64) -> u64 {
+    memory(LOAD, reg, size, base, disp)
+}
+/// Encodes `$jmc` with a condition code (`0x1`..`0xF`), two registers below 16 and a 36-bit address.
+pub fn jmc(cond: u64, lhs: usize, rhs: usize, address: u64) -> u64 {
+    bare(JMC) | ((cond & 0xF) << 44) | ((lhs as u64 & 0xF) << 40) | ((rhs as u64 & 0xF) << 36) | (address & 0xF_FFFF_FFFF)
+}
+/// Encodes `$jmci` with a condition code, a register below 16, an 8-bit immediate and a 32-bit address.
+pub fn jmci(cond: u64, reg: usize, imm: u64, address: u64) -> u64 {
+    bare(JMCI) | ((cond & 0xF) << 44) | ((reg as u64 & 0xF) << 40) | ((imm & 0xFF) << 32) | (address & 0xFFFF_FFFF)
+}
+/// Encodes `$cmov` with a `$jmc` condition code, two compared registers below 16, a source and a destination register.
+pub fn cmov(cond: u64, lhs: usize, rhs: usize, src: usize, dst: usize) -> u64 {
+    bare(CMOV) | ((cond & 0xF) << 44) | ((lhs as u64 & 0xF) << 40) | ((rhs as u64 & 0xF) << 36) | ((src as u64 & 0xFF) << 24) | ((dst as u64 & 0xFF) << 16)
+}
+/// Loads an arbitrary 64-bit constant into `reg`, using `scratch` when it does not fit into `$set`.
+pub fn load_const(reg: usize, scratch: usize, value: u64) -> Vec<u64> {
+    if value <= SET_MAX {
+        return vec![set(reg, value)]
+    }
+    vec![
+        set(reg, value >> 32),
+        set(scratch, 32),
+        ternary(SHL, reg, scratch, reg),
+        set(scratch, value & 0xFFFF_FFFF),
+        ternary(OR, reg, scratch, reg)
+    ]
+}
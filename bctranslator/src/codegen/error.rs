@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// An error produced while lowering the AST into instructions.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CodegenError {
+    /// An instruction was given operands that do not match its encoding, carrying its mnemonic.
+    InvalidOperands(String),
+    /// A jump or call refers to a label or function that is not defined.
+    UndefinedLabel(String),
+    /// A construct the VM has no instruction for, e.g. a floating-point remainder.
+    Unsupported(String),
+    /// An immediate operand does not fit into its instruction field.
+    ImmediateTooLarge(u64),
+    /// An expression needs more temporaries than there are allocatable registers.
+    RegistersExhausted,
+    /// A variable or function name is used without being defined.
+    UndefinedSymbol(String),
+    /// A label is placed more than once.
+    DuplicateLabel(String),
+    /// A constant integer division or remainder has a zero divisor.
+    DivisionByZero,
+    /// An operation mixes integer and floating-point values, carrying a description.
+    TypeError(String)
+}
+impl Display for CodegenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodegenError::InvalidOperands(mnemonic) => write!(f, "Invalid operands for {}", mnemonic),
+            CodegenError::UndefinedLabel(label) => write!(f, "Undefined label {}", label),
+            CodegenError::Unsupported(what) => write!(f, "{} is not supported", what),
+            CodegenError::ImmediateTooLarge(value) => write!(f, "Immediate {:#X} does not fit into its instruction", value),
+            CodegenError::RegistersExhausted => write!(f, "Expression is too complex, all registers are in use"),
+            CodegenError::UndefinedSymbol(name) => write!(f, "Undefined symbol {}", name),
+            CodegenError::DuplicateLabel(label) => write!(f, "Label {} is defined more than once", label),
+            CodegenError::DivisionByZero => write!(f, "Constant expression divides by zero"),
+            CodegenError::TypeError(description) => write!(f, "Type error: {}", description)
+        }
+    }
+}
+impl Error for CodegenError {}
+
+/// An error found by checking a translated program as a whole, see [stack_balance](crate::codegen::verify::stack_balance).
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImageError {
+    /// A function returns with a different stack depth than it was entered with, or reaches a statement with different
+    /// depths on different paths, carrying its name.
+    UnbalancedStack(String)
+}
+impl Display for ImageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::UnbalancedStack(function) => write!(f, "Function {} does not leave the stack balanced on every path", function)
+        }
+    }
+}
+impl Error for ImageError {}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use crate::codegen::encode;
+use crate::codegen::error::CodegenError;
+use crate::codegen::regalloc::RegisterAllocator;
+use crate::codegen::symbols::SymbolTable;
+use crate::parser::ast::{Node, Visitable};
+
+/// Address the VM loads the first instruction of a program at.
+pub const CODE_BASE: u64 = 0x1000_0000;
+
+/// State shared by all nodes while a program is lowered.
+///
+/// Jump targets are resolved by backpatching: a jump is emitted with address 0 and remembered,
+/// and [CodeGenerator::generate] fills in the address once every label and function is known.
+///
+/// Bytes placed with `$data` are collected into a data section that follows the code, see [CodeGenerator::data].
+#[derive(Debug)]
+pub struct CodeGenerator {
+    pub registers: RegisterAllocator,
+    pub symbols: SymbolTable,
+    /// Index of the word the statement being lowered will start at.
+    pub position: usize,
+    labels: HashMap<String, u64>,
+    fixups: Vec<Fixup>,
+    data: Vec<u8>,
+    data_symbols: HashMap<String, u64>,
+    data_base: u64
+}
+#[derive(Debug)]
+enum Target {
+    Label(String),
+    Function(String),
+    Data(String)
+}
+#[derive(Debug)]
+struct Fixup {
+    index: usize,
+    target: Target
+}
+impl CodeGenerator {
+    pub fn new() -> CodeGenerator {
+        Self {
+            registers: RegisterAllocator::new(),
+            symbols: SymbolTable::new(),
+            position: 0,
+            labels: HashMap::new(),
+            fixups: Vec::new(),
+            data: Vec::new(),
+            data_symbols: HashMap::new(),
+            data_base: CODE_BASE
+        }
+    }
+    /// Lowers a whole program and resolves its jumps.
+    ///
+    /// The data section is not part of the returned code. It is placed directly after it, see [CodeGenerator::data].
+    ///
+    /// # Returns
+    /// - `Ok(Vec<u64>)` with every jump pointing at its absolute address.
+    /// - `Err(CodegenError::UndefinedLabel)` or `Err(CodegenError::UndefinedSymbol)` if a jump, call or data
+    ///   reference has no target.
+    pub fn generate(&mut self, nodes: &[Node]) -> Result<Vec<u64>, CodegenError> {
+        let mut code = Vec::new();
+        for node in nodes {
+            self.position = code.len();
+            code.extend(node.accept(self)?);
+        }
+        self.data_base = CODE_BASE + code.len() as u64 * 8;
+        self.link(&mut code)?;
+        Ok(code)
+    }
+    /// The bytes placed with `$data`, in source order. They belong right after the generated code.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    /// Appends bytes to the data section, binding `name` to them if given.
+    ///
+    /// # Returns
+    /// - `Ok(u64)` with the offset of the bytes from the start of the data section.
+    /// - `Err(CodegenError::DuplicateLabel)` if `name` already names other data.
+    pub fn define_data(&mut self, name: Option<&str>, bytes: &[u8]) -> Result<u64, CodegenError> {
+        let offset = self.data.len() as u64;
+        if let Some(name) = name {
+            if self.data_symbols.insert(String::from(name), offset).is_some() {
+                return Err(CodegenError::DuplicateLabel(String::from(name)))
+            }
+        }
+        self.data.extend_from_slice(bytes);
+        Ok(offset)
+    }
+    /// Returns the absolute address of named data. Only meaningful once [CodeGenerator::generate] has finished.
+    pub fn data_address(&self, name: &str) -> Option<u64> {
+        self.data_symbols.get(name).map(|offset| self.data_base + offset)
+    }
+    /// Records that the word at `index` within the current statement loads the address of the data `name`.
+    pub fn address_of_data(&mut self, index: usize, name: &str) {
+        self.fixups.push(Fixup { index: self.position + index, target: Target::Data(String::from(name)) });
+    }
+    /// Byte offset of the current statement from the start of the program.
+    pub fn offset(&self) -> u64 {
+        self.position as u64 * 8
+    }
+    /// Places a label at the current statement.
+    pub fn define_label(&mut self, name: &str) -> Result<(), CodegenError> {
+        if self.labels.insert(String::from(name), self.offset()).is_some() {
+            return Err(CodegenError::DuplicateLabel(String::from(name)))
+        }
+        Ok(())
+    }
+    /// Records that the word at `index` within the current statement jumps to `label`.
+    pub fn jump_to_label(&mut self, index: usize, label: &str) {
+        self.fixups.push(Fixup { index: self.position + index, target: Target::Label(String::from(label)) });
+    }
+    /// Records that the word at `index` within the current statement calls `name`.
+    pub fn jump_to_function(&mut self, index: usize, name: &str) {
+        self.fixups.push(Fixup { index: self.position + index, target: Target::Function(String::from(name)) });
+    }
+    fn link(&self, code: &mut [u64]) -> Result<(), CodegenError> {
+        for fixup in &self.fixups {
+            let offset = match &fixup.target {
+                Target::Label(label) => *self.labels.get(label).ok_or_else(|| CodegenError::UndefinedLabel(label.clone()))?,
+                Target::Function(name) => self.symbols.function(name)?,
+                Target::Data(name) => self.data_address(name).ok_or_else(|| CodegenError::UndefinedSymbol(name.clone()))? - CODE_BASE
+            };
+            let word = &mut code[fixup.index];
+            let mask = match *word >> 48 {
+                encode::JMC => 0xF_FFFF_FFFF,
+                encode::JMCI => 0xFFFF_FFFF,
+                encode::LEA => encode::SET_MAX,
+                _ => 0xFFFF_FFFF_FFFF
+            };
+            *word = (*word & !mask) | ((CODE_BASE + offset) & mask);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::codegen::encode;
+use crate::codegen::error::CodegenError;
+use crate::lexer::token::TokenType;
+use crate::parser::ast::Operand;
+use crate::parser::ast::Operand::{Immediate, Register};
+
+/// Encodes a bare instruction statement such as `$add r1, r2, r3`.
+///
+/// Register operands are listed in the order the VM reads them, destination last.
+pub fn lower(op: &TokenType, operands: &[Operand]) -> Result<Vec<u64>, CodegenError> {
+    let word = match (op, operands) {
+        (TokenType::NOP, []) => encode::bare(encode::NOP),
+        (TokenType::HALT, []) => encode::bare(encode::HALT),
+        (TokenType::EXIT, []) => encode::unary(encode::EXIT, 0),
+        (TokenType::EXIT, [Immediate(code)]) if *code <= 0xFF => encode::unary(encode::EXIT, *code as usize),
+        (TokenType::SYSCALL, [Immediate(number)]) => {
+            if *number > 0xFFFF_FFFF_FFFF {
+                return Err(CodegenError::ImmediateTooLarge(*number))
+            }
+            encode::jump(encode::SYSCALL, *number)
+        }
+        (TokenType::SET, [Register(reg), Immediate(imm)]) => {
+            if *imm > encode::SET_MAX {
+                return Err(CodegenError::ImmediateTooLarge(*imm))
+            }
+            encode::set(*reg, *imm)
+        }
+        (TokenType::LEA, [Register(reg), Immediate(address)]) => {
+            if *address > encode::SET_MAX {
+                return Err(CodegenError::ImmediateTooLarge(*address))
+            }
+            encode::lea(*reg, *address)
+        }
+        (TokenType::PUSH | TokenType::POP | TokenType::FUEL | TokenType::INC | TokenType::DEC | TokenType::CALLR, [Register(reg)]) => encode::unary(unary_opcode(op), *reg),
+        (TokenType::MOVE | TokenType::CMP | TokenType::NOT | TokenType::NEG | TokenType::ABS | TokenType::POPCNT | TokenType::CLZ
+            | TokenType::CTZ | TokenType::BSWAP | TokenType::ITOF | TokenType::FTOI | TokenType::SWAP, [Register(a), Register(b)]) => {
+            encode::binary(binary_opcode(op), *a, *b)
+        }
+        (TokenType::CAS, [Register(addr), Register(expected), Register(new), Register(result)]) => {
+            encode::quaternary(encode::CAS, *addr, *expected, *new, *result)
+        }
+        (TokenType::ADDM | TokenType::SUBM, [Register(reg), Immediate(size), Register(base), Immediate(disp)]) if matches!(size, 8 | 16 | 32 | 64) => {
+            if *disp > 0xFF_FFFF {
+                return Err(CodegenError::ImmediateTooLarge(*disp))
+            }
+            let opcode = if *op == TokenType::ADDM { encode::ADDM } else { encode::SUBM };
+            encode::memory(opcode, *reg, *size as usize, *base, *disp)
+        }
+        (TokenType::REM, _) => return Err(CodegenError::Unsupported(String::from("$rem"))),
+        (_, [Register(a), Register(b), Register(c)]) if ternary_opcode(op).is_some() => {
+            encode::ternary(ternary_opcode(op).unwrap(), *a, *b, *c)
+        }
+        _ => return Err(CodegenError::InvalidOperands(mnemonic(op)))
+    };
+    Ok(vec![word])
+}
+
+/// Returns the opcode of a jump on a status flag (`$jz`..`$jno`), which takes a label operand.
+pub fn flag_jump_opcode(op: &TokenType) -> Option<u64> {
+    match op {
+        TokenType::JZ => Some(encode::JZ),
+        TokenType::JNZ => Some(encode::JNZ),
+        TokenType::JS => Some(encode::JS),
+        TokenType::JNS => Some(encode::JNS),
+        TokenType::JC => Some(encode::JC),
+        TokenType::JNC => Some(encode::JNC),
+        TokenType::JO => Some(encode::JO),
+        TokenType::JNO => Some(encode::JNO),
+        _ => None
+    }
+}
+
+fn unary_opcode(op: &TokenType) -> u64 {
+    match op {
+        TokenType::PUSH => encode::PUSH,
+        TokenType::POP => encode::POP,
+        TokenType::INC => encode::INC,
+        TokenType::DEC => encode::DEC,
+        TokenType::CALLR => encode::CALLR,
+        _ => encode::FUEL
+    }
+}
+
+fn binary_opcode(op: &TokenType) -> u64 {
+    match op {
+        TokenType::MOVE => encode::MOVE,
+        TokenType::CMP => encode::CMP,
+        TokenType::NOT => encode::NOT,
+        TokenType::NEG => encode::NEG,
+        TokenType::ABS => encode::ABS,
+        TokenType::POPCNT => encode::POPCNT,
+        TokenType::CLZ => encode::CLZ,
+        TokenType::CTZ => encode::CTZ,
+        TokenType::BSWAP => encode::BSWAP,
+        TokenType::ITOF => encode::ITOF,
+        TokenType::SWAP => encode::SWAP,
+        _ => encode::FTOI
+    }
+}
+
+fn ternary_opcode(op: &TokenType) -> Option<u64> {
+    match op {
+        TokenType::ADD => Some(encode::ADD),
+        TokenType::SUB => Some(encode::SUB),
+        TokenType::MUL => Some(encode::MUL),
+        TokenType::DIV => Some(encode::DIV),
+        TokenType::ADDC => Some(encode::ADDC),
+        TokenType::MULC => Some(encode::MULC),
+        TokenType::ADC => Some(encode::ADC),
+        TokenType::SDIV => Some(encode::SDIV),
+        TokenType::SREM => Some(encode::SREM),
+        TokenType::FADD => Some(encode::FADD),
+        TokenType::FSUB => Some(encode::FSUB),
+        TokenType::FMUL => Some(encode::FMUL),
+        TokenType::FDIV => Some(encode::FDIV),
+        TokenType::AND => Some(encode::AND),
+        TokenType::OR => Some(encode::OR),
+        TokenType::XOR => Some(encode::XOR),
+        TokenType::SHL => Some(encode::SHL),
+        TokenType::SHR => Some(encode::SHR),
+        TokenType::SAR => Some(encode::SAR),
+        TokenType::MEMCPY => Some(encode::MEMCPY),
+        _ => None
+    }
+}
+
+/// Returns the `$keyword` spelling of an instruction for error messages.
+pub fn mnemonic(op: &TokenType) -> String {
+    op.to_string()
+}
@@ -0,0 +1,7 @@
+pub mod encode;
+pub mod error;
+pub mod generator;
+pub mod instruction;
+pub mod regalloc;
+pub mod symbols;
+pub mod verify;
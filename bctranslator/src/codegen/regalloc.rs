@@ -0,0 +1,42 @@
+use crate::codegen::error::CodegenError;
+
+/// The highest register handed out for temporaries and variables.
+///
+/// r12 and r13 hold the stack base and stack pointer and are never allocated.
+pub const LAST_ALLOCATABLE: usize = 11;
+/// The register a function returns its value in, set by `$ret value` and read by the caller after `$call`.
+pub const RETURN_REGISTER: usize = 0;
+/// How many arguments a `$call` passes in registers, starting at r0.
+///
+/// Further arguments are pushed onto the stack, the last one first, so the callee finds argument `n` at
+/// `r13 + 8 * (n - ARGUMENT_REGISTERS + 1)`, right above its return address. The callee may overwrite any allocatable
+/// register, so the caller pushes the registers of its variables before the call and pops them afterwards.
+pub const ARGUMENT_REGISTERS: usize = 4;
+
+/// Hands out the registers r0..=r11 for temporaries, always picking the lowest free one.
+#[derive(Debug)]
+pub struct RegisterAllocator {
+    free: Vec<usize>
+}
+impl RegisterAllocator {
+    pub fn new() -> RegisterAllocator {
+        Self {
+            free: (0..=LAST_ALLOCATABLE).rev().collect()
+        }
+    }
+    /// Takes the lowest free register.
+    pub fn allocate(&mut self) -> Result<usize, CodegenError> {
+        self.free.pop().ok_or(CodegenError::RegistersExhausted)
+    }
+    /// Takes a specific register out of the pool, e.g. one holding a parameter. It comes back with [RegisterAllocator::release].
+    pub fn reserve(&mut self, reg: usize) {
+        self.free.retain(|&free| free != reg);
+    }
+    /// Returns a register to the pool. Releasing a register that is already free has no effect.
+    pub fn release(&mut self, reg: usize) {
+        if reg <= LAST_ALLOCATABLE && !self.free.contains(&reg) {
+            self.free.push(reg);
+            self.free.sort_unstable_by(|a, b| b.cmp(a));
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use crate::codegen::error::CodegenError;
+use crate::parser::ast::ValueType;
+
+/// Maps source names to the places codegen keeps them.
+///
+/// Variables live in registers and are scoped, so leaving a `$funcdef` body forgets them. Each variable also keeps the
+/// type of its value for type checking, unless it was initialized from a raw register.
+/// Functions are global and map to their byte offset from the start of the program.
+#[derive(Debug)]
+pub struct SymbolTable {
+    scopes: Vec<HashMap<String, (usize, Option<ValueType>)>>,
+    functions: HashMap<String, u64>
+}
+impl SymbolTable {
+    /// Creates a table with a single, global scope.
+    pub fn new() -> SymbolTable {
+        Self {
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new()
+        }
+    }
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+    /// Leaves the innermost scope.
+    ///
+    /// # Returns
+    /// The registers its variables occupied, so the caller can release them. The global scope is never popped.
+    pub fn pop_scope(&mut self) -> Vec<usize> {
+        if self.scopes.len() == 1 {
+            return Vec::new()
+        }
+        self.scopes.pop().map(|scope| scope.into_values().map(|(reg, _)| reg).collect()).unwrap_or_default()
+    }
+    /// Binds a variable in the innermost scope.
+    ///
+    /// # Returns
+    /// The register the name was previously bound to in the same scope, if any.
+    pub fn define_variable(&mut self, name: &str, reg: usize, ty: Option<ValueType>) -> Option<usize> {
+        self.scopes.last_mut().and_then(|scope| scope.insert(String::from(name), (reg, ty))).map(|(reg, _)| reg)
+    }
+    /// Resolves a variable to its register, searching from the innermost scope outwards.
+    pub fn variable(&self, name: &str) -> Result<usize, CodegenError> {
+        self.lookup(name).map(|(reg, _)| reg)
+    }
+    /// Resolves the type of a variable's value, `None` if it is untyped.
+    pub fn variable_type(&self, name: &str) -> Result<Option<ValueType>, CodegenError> {
+        self.lookup(name).map(|(_, ty)| ty)
+    }
+    /// Returns the registers of every variable in scope, including shadowed ones, in ascending order.
+    pub fn variable_registers(&self) -> Vec<usize> {
+        let mut regs: Vec<usize> = self.scopes.iter().flat_map(|scope| scope.values().map(|&(reg, _)| reg)).collect();
+        regs.sort_unstable();
+        regs.dedup();
+        regs
+    }
+    /// Rebinds every variable held in `from` to `to`, after codegen moved its value there.
+    pub fn relocate(&mut self, from: usize, to: usize) {
+        for (reg, _) in self.scopes.iter_mut().flat_map(|scope| scope.values_mut()) {
+            if *reg == from {
+                *reg = to;
+            }
+        }
+    }
+    fn lookup(&self, name: &str) -> Result<(usize, Option<ValueType>), CodegenError> {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(name).copied())
+            .ok_or_else(|| CodegenError::UndefinedSymbol(String::from(name)))
+    }
+    pub fn define_function(&mut self, name: &str, offset: u64) {
+        self.functions.insert(String::from(name), offset);
+    }
+    /// Resolves a function to its byte offset.
+    pub fn function(&self, name: &str) -> Result<u64, CodegenError> {
+        self.functions.get(name).copied().ok_or_else(|| CodegenError::UndefinedSymbol(String::from(name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inner_scopes_shadow_and_release_their_variables() {
+        let mut symbols = SymbolTable::new();
+        symbols.define_variable("a", 0, Some(ValueType::Int));
+        symbols.push_scope();
+        symbols.define_variable("a", 3, Some(ValueType::Float));
+        assert_eq!(symbols.variable("a"), Ok(3));
+        assert_eq!(symbols.variable_type("a"), Ok(Some(ValueType::Float)));
+        assert_eq!(symbols.pop_scope(), vec![3]);
+        assert_eq!(symbols.variable("a"), Ok(0));
+        assert_eq!(symbols.variable("b"), Err(CodegenError::UndefinedSymbol(String::from("b"))));
+    }
+}
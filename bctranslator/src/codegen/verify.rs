@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use crate::codegen::error::ImageError;
+use crate::codegen::instruction;
+use crate::lexer::token::TokenType;
+use crate::parser::ast::{Node, Operand, Statement};
+
+/// Checks that every `$funcdef` leaves the stack as it found it.
+///
+/// Every path through a function body is followed, counting `$push` as one slot and `$pop` as minus one. `$call`
+/// pops whatever it pushes, so it counts as zero. `$halt`, `$exit`, the end of the body and jumps to labels outside the
+/// function end a path without a check.
+///
+/// # Returns
+/// - `Ok(())` if all paths reach each statement with the same stack depth and every `$ret` with the depth of the entry.
+/// - `Err(ImageError::UnbalancedStack)` with the name of the first function that does not.
+pub fn stack_balance(nodes: &[Node]) -> Result<(), ImageError> {
+    for node in nodes {
+        if let Node::Statement(Statement::FuncDef { name, body, .. }) = node {
+            if !is_balanced(body) {
+                return Err(ImageError::UnbalancedStack(name.clone()))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_balanced(body: &[Node]) -> bool {
+    let labels: HashMap<&str, usize> = body.iter().enumerate()
+        .filter_map(|(i, node)| match node {
+            Node::Statement(Statement::Label(label)) => Some((label.as_str(), i)),
+            _ => None
+        })
+        .collect();
+    let target = |label: &str, depth: i64| labels.get(label).map(|&i| (i, depth));
+    let mut depths: Vec<Option<i64>> = vec![None; body.len()];
+    let mut pending = vec![(0, 0)];
+    while let Some((i, depth)) = pending.pop() {
+        let Some(node) = body.get(i) else {
+            continue
+        };
+        match depths[i] {
+            Some(seen) if seen == depth => continue,
+            Some(_) => return false,
+            None => depths[i] = Some(depth)
+        }
+        let Node::Statement(statement) = node;
+        match statement {
+            Statement::Ret { .. } if depth != 0 => return false,
+            Statement::Ret { .. } => {}
+            Statement::Jmp { label } => pending.extend(target(label, depth)),
+            Statement::Jmc { label, .. } | Statement::Jmci { label, .. } => {
+                pending.extend(target(label, depth));
+                pending.push((i + 1, depth));
+            }
+            Statement::Instruction { op, operands } => match (op, operands.as_slice()) {
+                (TokenType::HALT | TokenType::EXIT, _) => {}
+                (TokenType::PUSH, _) => pending.push((i + 1, depth + 1)),
+                (TokenType::POP, _) => pending.push((i + 1, depth - 1)),
+                (_, [Operand::Label(label)]) if instruction::flag_jump_opcode(op).is_some() => {
+                    pending.extend(target(label, depth));
+                    pending.push((i + 1, depth));
+                }
+                _ => pending.push((i + 1, depth))
+            },
+            _ => pending.push((i + 1, depth))
+        }
+    }
+    true
+}
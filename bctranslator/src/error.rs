@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::codegen::error::{CodegenError, ImageError};
+use crate::lexer::error::LexError;
+use crate::parser::error::ParseError;
+
+/// An error from any stage of [translate](crate::translate).
+#[derive(Clone, Debug, PartialEq)]
+pub enum TranslateError {
+    Lex(LexError),
+    Parse(ParseError),
+    Codegen(CodegenError),
+    Image(ImageError)
+}
+impl Display for TranslateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranslateError::Lex(error) => write!(f, "{}", error),
+            TranslateError::Parse(error) => write!(f, "{}", error),
+            TranslateError::Codegen(error) => write!(f, "{}", error),
+            TranslateError::Image(error) => write!(f, "{}", error)
+        }
+    }
+}
+impl Error for TranslateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TranslateError::Lex(error) => Some(error),
+            TranslateError::Parse(error) => Some(error),
+            TranslateError::Codegen(error) => Some(error),
+            TranslateError::Image(error) => Some(error)
+        }
+    }
+}
+impl From<LexError> for TranslateError {
+    fn from(error: LexError) -> Self {
+        TranslateError::Lex(error)
+    }
+}
+impl From<ParseError> for TranslateError {
+    fn from(error: ParseError) -> Self {
+        TranslateError::Parse(error)
+    }
+}
+impl From<CodegenError> for TranslateError {
+    fn from(error: CodegenError) -> Self {
+        TranslateError::Codegen(error)
+    }
+}
+impl From<ImageError> for TranslateError {
+    fn from(error: ImageError) -> Self {
+        TranslateError::Image(error)
+    }
+}
@@ -0,0 +1,37 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// An error produced while splitting source text into tokens.
+///
+/// Every variant carries the character position at which the problem was found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexError {
+    /// A character that cannot start any token.
+    UnexpectedChar { chr: char, pos: usize },
+    /// A `$` keyword that the language does not know.
+    UnexpectedKeyword { keyword: String, pos: usize },
+    /// A number literal that cannot be read, e.g. with a second decimal point.
+    MalformedNumber { number: String, pos: usize },
+    /// A register operand whose index is not in `0..32`.
+    InvalidRegister { register: String, pos: usize },
+    /// An integer literal whose value does not fit into the type given by its suffix.
+    LiteralOutOfRange { literal: String, pos: usize },
+    /// A string literal without its closing quote. The position is that of the opening quote.
+    UnterminatedString { pos: usize },
+    /// A backslash in a string literal followed by a character other than `n`, `t`, `"` or `\`.
+    InvalidEscape { escape: char, pos: usize }
+}
+impl Display for LexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnexpectedChar { chr, pos } => write!(f, "Unexpected char {} at pos {}", chr, pos),
+            LexError::UnexpectedKeyword { keyword, pos } => write!(f, "Unexpected keyword {} at pos {}", keyword, pos),
+            LexError::MalformedNumber { number, pos } => write!(f, "Malformed number {} at pos {}", number, pos),
+            LexError::InvalidRegister { register, pos } => write!(f, "Invalid register {} at pos {}. Registers are r0..r31", register, pos),
+            LexError::LiteralOutOfRange { literal, pos } => write!(f, "Literal {} at pos {} does not fit into its type", literal, pos),
+            LexError::UnterminatedString { pos } => write!(f, "Unterminated string starting at pos {}", pos),
+            LexError::InvalidEscape { escape, pos } => write!(f, "Invalid escape \\{} at pos {}", escape, pos)
+        }
+    }
+}
+impl Error for LexError {}
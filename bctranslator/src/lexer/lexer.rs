@@ -1,11 +1,14 @@
 use std::collections::HashMap;
+use crate::lexer::error::LexError;
 use crate::lexer::token::{Token, TokenType};
-use crate::lexer::token::TokenType::{ADD, ADDRESS, AT, CALL, COLON, COMMA, DIV, DOLLAR, DOUBLEKW, EOF, EQ, EXIT, FLOATING, FLOATKW, FUNCDEF, HALT, I16KW, I32KW, I64KW, I8KW, IDENT, INTEGER, JMC, JMP, LBRACE, LPAREN, MINUS, MOVE, MUL, PARAMS, PERCENT, PERIOD, PLUS, RBRACE, REM, RET, RPAREN, SET, SLASH, STAR, SUB, UNDER, UNSIGNEDKW, VARDEF};
+use crate::lexer::token::TokenType::{ABS, ADC, ADD, ADDC, ADDM, ADDRESS, AND, AT, BSWAP, CALL, CALLR, CAS, CLZ, CMOV, CMP, COLON, COMMA, CTZ, DATA, DEC, DIV, DOLLAR, DOUBLEKW, EOF, EQ, EXIT, FADD, FDIV, FLOATING, FLOATKW, FMUL, FSUB, FTOI, FUEL, FUNCDEF, HALT, I16KW, I32KW, I64KW, I8KW, IDENT, INC, INTEGER, ITOF, JC, JMC, JMCI, JMP, JNC, JNO, JNS, JNZ, JO, JS, JZ, LBRACE, LEA, LPAREN, MEMCPY, MINUS, MOVE, MUL, MULC, NEG, NOP, NOT, OR, PARAMS, PERCENT, PERIOD, PLUS, POP, POPCNT, PUSH, RBRACE, REGISTER, REM, RET, RPAREN, SAR, SDIV, SET, SHL, SHR, SLASH, SREM, STAR, STRING, SUB, SUBM, SWAP, SYSCALL, UNDER, UNSIGNEDKW, VARDEF, XOR};
 use crate::utils::stringutils::StringBuilder;
 
 pub struct Lexer {
     pos: usize,
-    input: String,
+    line: usize,
+    column: usize,
+    input: Vec<char>,
     cur: char,
     size: usize,
     output: Vec<Token>,
@@ -15,10 +18,13 @@ pub struct Lexer {
 impl Lexer {
     pub fn new(input: String) -> Lexer {
         let pos: usize = 0;
-        let cur = (&input).chars().nth(pos).expect("Empty input");
-        let size = (&input).len();
+        let input: Vec<char> = input.chars().collect();
+        let cur = input.get(pos).copied().unwrap_or('\0');
+        let size = input.len();
         Self {
             pos,
+            line: 1,
+            column: 1,
             input,
             cur,
             size,
@@ -26,77 +32,135 @@ impl Lexer {
             buffer: StringBuilder::new()
         }
     }
-    pub fn tokenize(&mut self) -> Vec<Token> {
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, LexError> {
         while self.has_next() {
             match self.cur {
-                '$' =>  self.tokenize_keyword(),
-                '.' => { self.output.push(Token::new(PERIOD, String::from("."), self.pos)); self.next() }
-                ',' => { self.output.push(Token::new(COMMA, String::from(","), self.pos)); self.next() }
-                '_' => { self.output.push(Token::new(UNDER, String::from("_"), self.pos)); self.next() }
-                ':' => { self.output.push(Token::new(COLON, String::from(":"), self.pos)); self.next() }
-                '(' => { self.output.push(Token::new(LPAREN, String::from("("), self.pos)); self.next() }
-                ')' => { self.output.push(Token::new(RPAREN, String::from(")"), self.pos)); self.next() }
-                '{' => { self.output.push(Token::new(LBRACE, String::from("{"), self.pos)); self.next() }
-                '}' => { self.output.push(Token::new(RBRACE, String::from("}"), self.pos)); self.next() }
-                '=' => { self.output.push(Token::new(EQ, String::from("="), self.pos)); self.next() }
-                '+' => { self.output.push(Token::new(PLUS, String::from("+"), self.pos)); self.next() }
-                '-' => { self.output.push(Token::new(MINUS, String::from("-"), self.pos)); self.next() }
-                '*' => { self.output.push(Token::new(STAR, String::from("*"), self.pos)); self.next() }
-                '/' => { self.output.push(Token::new(SLASH, String::from("/"), self.pos)); self.next() }
-                '%' => { self.output.push(Token::new(PERCENT, String::from("%"), self.pos)); self.next() }
-                '\n' | '\t' => { self.next() }
+                '$' =>  self.tokenize_keyword()?,
+                '.' => self.single(PERIOD),
+                ',' => self.single(COMMA),
+                '_' => self.single(UNDER),
+                ':' => self.single(COLON),
+                '(' => self.single(LPAREN),
+                ')' => self.single(RPAREN),
+                '{' => self.single(LBRACE),
+                '}' => self.single(RBRACE),
+                '=' => self.single(EQ),
+                '+' => self.single(PLUS),
+                '-' if self.starts_negative_literal() => self.tokenize_num()?,
+                '-' => self.single(MINUS),
+                '*' => self.single(STAR),
+                '/' => self.single(SLASH),
+                '%' => self.single(PERCENT),
+                '"' => self.tokenize_string()?,
+                '\n' | '\r' | '\t' => { self.next() }
                 '@' => {
-                    self.output.push(Token::new(AT, String::from("@"), self.pos));
+                    self.output.push(Token::new(AT, String::from("@"), self.pos).at(self.line, self.column));
                     self.tokenize_address();
                 }
-                '\0' => {
-                    self.output.push(Token::new(EOF, String::from("\0"), 999999));
-                    break
-                }
+                '\0' => break,
                 _ => {
-                    if self.cur == '0' && self.peek(1) == 'x' { self.tokenize_hexnum() }
-                    else if self.cur.is_digit(10) { self.tokenize_num() }
+                    if self.matches_ahead("0x") { self.tokenize_hexnum()? }
+                    else if self.cur.is_digit(10) { self.tokenize_num()? }
+                    else if self.cur == 'r' && self.peek(1).is_digit(10) { self.tokenize_register()? }
                     else if self.cur.is_alphabetic() { self.tokenize_ident() }
                     else if self.cur.is_whitespace() { self.next() }
-                    else { panic!("Unexpected char {} at pos {}", self.cur, self.pos) }
+                    else { return Err(LexError::UnexpectedChar { chr: self.cur, pos: self.pos }) }
                 }
             }
         }
+        self.output.push(Token::with_len(EOF, String::from("\0"), self.pos, 0).at(self.line, self.column));
         let mut res: Vec<Token> = Vec::new();
         self.output.iter().clone().for_each(|x| {
             res.push(x.clone())
         });
-        res
+        Ok(res)
+    }
+    /// Pushes a single-character token for the current character and advances past it.
+    fn single(&mut self, r#type: TokenType) {
+        self.output.push(Token::new(r#type, self.cur.to_string(), self.pos).at(self.line, self.column));
+        self.next()
     }
     fn tokenize_address(&mut self) {
+        let (start, line, column) = (self.pos, self.line, self.column);
         self.buffer.clear();
         while self.cur.is_digit(16) {
             self.buffer.push(self.cur);
             self.next();
         }
         let address = self.buffer.pack();
-        self.output.push(Token::new(ADDRESS, address, self.pos))
+        self.output.push(Token::with_len(ADDRESS, address, start, self.pos - start).at(line, column))
     }
-    fn tokenize_keyword(&mut self) {
+    fn tokenize_keyword(&mut self) -> Result<(), LexError> {
+        let (start, line, column) = (self.pos, self.line, self.column);
         self.buffer.clear();
         let mut keywords: HashMap<&str, TokenType> = HashMap::from(
-            [("$add", ADD),
+            [("$nop", NOP),
+                ("$add", ADD),
                 ("$sub", SUB),
                 ("$mul", MUL),
                 ("$div", DIV),
                 ("$rem", REM),
+                ("$sdiv", SDIV),
+                ("$srem", SREM),
+                ("$fadd", FADD),
+                ("$fsub", FSUB),
+                ("$fmul", FMUL),
+                ("$fdiv", FDIV),
+                ("$itof", ITOF),
+                ("$ftoi", FTOI),
+                ("$and", AND),
+                ("$or", OR),
+                ("$xor", XOR),
+                ("$not", NOT),
+                ("$shl", SHL),
+                ("$shr", SHR),
+                ("$sar", SAR),
+                ("$popcnt", POPCNT),
+                ("$clz", CLZ),
+                ("$ctz", CTZ),
+                ("$bswap", BSWAP),
+                ("$memcpy", MEMCPY),
+                ("$swap", SWAP),
+                ("$inc", INC),
+                ("$dec", DEC),
+                ("$addc", ADDC),
+                ("$mulc", MULC),
+                ("$adc", ADC),
+                ("$neg", NEG),
+                ("$abs", ABS),
+                ("$push", PUSH),
+                ("$pop", POP),
                 ("$move", MOVE),
                 ("$set", SET),
+                ("$lea", LEA),
+                ("$cmov", CMOV),
+                ("$cas", CAS),
+                ("$addm", ADDM),
+                ("$subm", SUBM),
                 ("$jmp", JMP),
                 ("$jmc", JMC),
+                ("$jmci", JMCI),
+                ("$cmp", CMP),
+                ("$jz", JZ),
+                ("$jnz", JNZ),
+                ("$js", JS),
+                ("$jns", JNS),
+                ("$jc", JC),
+                ("$jnc", JNC),
+                ("$jo", JO),
+                ("$jno", JNO),
                 ("$funcdef", FUNCDEF),
                 ("$params", PARAMS),
                 ("$vardef", VARDEF),
+                ("$data", DATA),
                 ("$ret", RET),
                 ("$call", CALL),
+                ("$callr", CALLR),
                 ("$halt", HALT),
                 ("$exit", EXIT),
                 ("$quit", EXIT),
+                ("$fuel", FUEL),
+                ("$syscall", SYSCALL),
                 ("$i8", I8KW),
                 ("$i16", I16KW),
                 ("$i32", I32KW),
@@ -115,15 +179,33 @@ impl Lexer {
         }
         let keyword = self.buffer.pack();
         if let Some(r#type) = keywords.get_mut(keyword.as_str()) {
-            self.output.push(Token::new(r#type.clone(), keyword, self.pos))
+            self.output.push(Token::with_len(r#type.clone(), keyword, start, self.pos - start).at(line, column));
+            Ok(())
         } else {
-            panic!("Unexpected keyword {}", keyword)
+            Err(LexError::UnexpectedKeyword { keyword, pos: start })
         }
     }
-    fn tokenize_num(&mut self) {
+    /// Checks whether a `-` starts a negative literal rather than a subtraction.
+    ///
+    /// That is the case when a decimal digit follows immediately and the previous token cannot end an operand,
+    /// e.g. after `=`, `,` or `(`, so `1 - 2` and `1 -2` still subtract.
+    fn starts_negative_literal(&self) -> bool {
+        let operand_ends = matches!(
+            self.output.last().map(|token| &token.r#type),
+            Some(INTEGER { .. } | FLOATING { .. } | REGISTER { .. } | IDENT | RPAREN)
+        );
+        self.peek(1).is_digit(10) && !(self.peek(1) == '0' && self.peek(2) == 'x') && !operand_ends
+    }
+    fn tokenize_num(&mut self) -> Result<(), LexError> {
         let mut is_floating = false;
         let mut is_float = false;
+        let (start, line, column) = (self.pos, self.line, self.column);
         self.buffer.clear();
+        let negative = self.cur == '-';
+        if negative {
+            self.buffer.push(self.cur);
+            self.next();
+        }
         loop {
             if self.cur.is_digit(10) {
                 self.buffer.push(self.cur);
@@ -133,25 +215,36 @@ impl Lexer {
                 self.buffer.push(self.cur);
                 self.next();
             } else if self.cur == '.' && is_floating {
-                panic!("Unexpected [.] in floating number. Number already floating")
+                self.buffer.push(self.cur);
+                return Err(LexError::MalformedNumber { number: self.buffer.pack(), pos: start })
             } else if self.cur == 'f' {
                 is_float = true;
                 self.next();
                 break
             } else { break }
         }
+        if self.buffer.last() == Some('.') {
+            return Err(LexError::MalformedNumber { number: self.buffer.pack(), pos: start })
+        }
         let number = self.buffer.pack();
         if is_floating {
             if is_float {
-                self.output.push(Token::new(FLOATING { is_double: false }, number, self.pos))
+                self.output.push(Token::with_len(FLOATING { is_double: false }, number, start, self.pos - start).at(line, column))
             } else {
-                self.output.push(Token::new(FLOATING { is_double: true }, number, self.pos))
+                self.output.push(Token::with_len(FLOATING { is_double: true }, number, start, self.pos - start).at(line, column))
             }
         } else {
-            self.output.push(Token::new(INTEGER { unsigned: false, size: 32 }, number, self.pos))
+            let value = if negative { number.parse::<i64>().map(|value| value as u64) } else { number.parse::<u64>() };
+            let Ok(value) = value else {
+                return Err(LexError::MalformedNumber { number, pos: start })
+            };
+            let (unsigned, size) = self.integer_suffix(value, negative, false, start)?;
+            self.output.push(Token::with_len(INTEGER { unsigned, size }, number, start, self.pos - start).at(line, column).parsed(value))
         }
+        Ok(())
     }
-    fn tokenize_hexnum(&mut self) {
+    fn tokenize_hexnum(&mut self) -> Result<(), LexError> {
+        let (start, line, column) = (self.pos, self.line, self.column);
         self.buffer.clear();
         self.next();
         self.next();
@@ -159,11 +252,101 @@ impl Lexer {
             self.buffer.push(self.cur);
             self.next()
         }
+        if self.buffer.is_empty() {
+            return Err(LexError::MalformedNumber { number: String::from("0x"), pos: start })
+        }
         let number = self.buffer.pack();
-        self.output.push(Token::new(INTEGER { unsigned: true, size: 32 }, number, self.pos))
-
+        let Ok(value) = u64::from_str_radix(&number, 16) else {
+            return Err(LexError::MalformedNumber { number: format!("0x{}", number), pos: start })
+        };
+        let (unsigned, size) = self.integer_suffix(value, false, true, start)?;
+        self.output.push(Token::with_len(INTEGER { unsigned, size }, number, start, self.pos - start).at(line, column).parsed(value));
+        Ok(())
+    }
+    /// Reads an optional `i8`..`i64`/`u8`..`u64` suffix after an integer literal.
+    ///
+    /// Without a suffix the literal is 32 bits wide with the given default signedness.
+    /// With a suffix the value must fit into the declared type. A `negative` value is given in two's complement
+    /// and only fits signed types.
+    fn integer_suffix(&mut self, value: u64, negative: bool, unsigned: bool, start: usize) -> Result<(bool, usize), LexError> {
+        if !matches!(self.cur, 'i' | 'u') || !self.peek(1).is_digit(10) {
+            return Ok((unsigned, 32))
+        }
+        let unsigned = self.cur == 'u';
+        let mut suffix = String::from(self.cur);
+        self.next();
+        while self.cur.is_digit(10) {
+            suffix.push(self.cur);
+            self.next()
+        }
+        let literal = self.input[start..self.pos].iter().collect::<String>();
+        let size = match suffix[1..].parse::<usize>() {
+            Ok(size @ (8 | 16 | 32 | 64)) => size,
+            _ => return Err(LexError::MalformedNumber { number: literal, pos: start })
+        };
+        let fits = match (negative, unsigned) {
+            (true, true) => false,
+            (true, false) => (value as i64) >= i64::MIN >> (64 - size),
+            (false, true) => value <= u64::MAX >> (64 - size),
+            (false, false) => value <= u64::MAX >> (65 - size)
+        };
+        if !fits {
+            return Err(LexError::LiteralOutOfRange { literal, pos: start })
+        }
+        Ok((unsigned, size))
+    }
+    fn tokenize_register(&mut self) -> Result<(), LexError> {
+        let (start, line, column) = (self.pos, self.line, self.column);
+        self.buffer.clear();
+        self.buffer.push(self.cur);
+        self.next();
+        while self.cur.is_digit(10) {
+            self.buffer.push(self.cur);
+            self.next()
+        }
+        let register = self.buffer.pack();
+        match register[1..].parse::<usize>() {
+            Ok(index) if index < 32 => {
+                self.output.push(Token::with_len(REGISTER { index }, register, start, self.pos - start).at(line, column));
+                Ok(())
+            }
+            _ => Err(LexError::InvalidRegister { register, pos: start })
+        }
+    }
+    /// Lexes a double-quoted string literal. The token's value is the decoded text, without quotes.
+    ///
+    /// Supported escapes are `\n`, `\t`, `\"` and `\\`.
+    fn tokenize_string(&mut self) -> Result<(), LexError> {
+        let (start, line, column) = (self.pos, self.line, self.column);
+        self.buffer.clear();
+        self.next();
+        loop {
+            match self.cur {
+                '"' => break,
+                '\\' => {
+                    let escape = self.peek(1);
+                    self.buffer.push(match escape {
+                        'n' => '\n',
+                        't' => '\t',
+                        '"' => '"',
+                        '\\' => '\\',
+                        _ if self.pos + 1 >= self.size => return Err(LexError::UnterminatedString { pos: start }),
+                        _ => return Err(LexError::InvalidEscape { escape, pos: self.pos })
+                    });
+                    self.next();
+                }
+                _ if !self.has_next() => return Err(LexError::UnterminatedString { pos: start }),
+                chr => self.buffer.push(chr)
+            }
+            self.next();
+        }
+        self.next();
+        let text = self.buffer.pack();
+        self.output.push(Token::with_len(STRING, text, start, self.pos - start).at(line, column));
+        Ok(())
     }
     fn tokenize_ident(&mut self) {
+        let (start, line, column) = (self.pos, self.line, self.column);
         self.buffer.clear();
         loop {
             if self.cur.is_alphabetic() || self.cur == '_' {
@@ -174,24 +357,220 @@ impl Lexer {
             }
         }
         let ident = self.buffer.pack();
-        self.output.push(Token::new(IDENT, ident, self.pos))
+        self.output.push(Token::with_len(IDENT, ident, start, self.pos - start).at(line, column))
     }
     fn has_next(&self) -> bool {
         self.pos < self.size
     }
+    /// Advances to the next character, tracking line and column.
+    ///
+    /// `\r\n` counts as a single line break, as does a lone `\r`.
     fn next(&mut self) {
-        self.pos += 1;
-        if let Some(chr) = self.input.clone().chars().nth(self.pos) {
-            self.cur = chr
+        if self.cur == '\n' || (self.cur == '\r' && !self.matches_ahead("\r\n")) {
+            self.line += 1;
+            self.column = 1;
         } else {
-            self.cur = '\0'
+            self.column += 1;
         }
+        self.pos += 1;
+        self.cur = self.peek(0)
     }
     fn peek(&self, offset: usize) -> char {
-        if let Some(chr) = self.input.clone().chars().nth(self.pos + offset) {
-            chr
-        } else {
-            '\0'
+        self.input.get(self.pos + offset).copied().unwrap_or('\0')
+    }
+    /// Checks whether the input continues with `text`, starting at the current character, without consuming it.
+    fn matches_ahead(&self, text: &str) -> bool {
+        let mut ahead = self.input[self.pos.min(self.size)..].iter();
+        text.chars().all(|expected| ahead.next() == Some(&expected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyword_token_spans_whole_lexeme() {
+        let mut lexer = Lexer::new(String::from("  $funcdef entry"));
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[0].span(), (2, 10));
+        assert_eq!(tokens[1].span(), (11, 16));
+    }
+
+    #[test]
+    fn accessors_expose_type_value_and_position() {
+        let tokens = Lexer::new(String::from(" $add r1")).tokenize().unwrap();
+        assert_eq!(tokens[0].ty(), &ADD);
+        assert_eq!(tokens[0].value(), "$add");
+        assert_eq!(tokens[0].pos(), 1);
+        assert_eq!(tokens[1].ty(), &REGISTER { index: 1 });
+    }
+
+    #[test]
+    fn token_types_display_as_written() {
+        assert_eq!(format!("{}", ADD), "$add");
+        assert_eq!(format!("{}", I16KW), "$i16");
+        assert_eq!(format!("{}", INTEGER { unsigned: true, size: 32 }), "integer(u32)");
+        assert_eq!(format!("{}", REGISTER { index: 7 }), "r7");
+        assert_eq!(format!("{}", LBRACE), "{");
+    }
+
+    #[test]
+    fn tokens_compare_by_type_and_lexeme() {
+        assert_eq!(ADD, ADD);
+        assert_ne!(INTEGER { unsigned: false, size: 32 }, INTEGER { unsigned: false, size: 64 });
+        let tokens = Lexer::new(String::from("1i32 1i64 1i32")).tokenize().unwrap();
+        assert_ne!(tokens[0], tokens[1]);
+        assert_eq!(tokens[0], tokens[2]);
+    }
+
+    #[test]
+    fn tokens_record_line_and_column() {
+        let mut lexer = Lexer::new(String::from("$funcdef entry {\n\t$ret }"));
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[0].location(), (1, 1));
+        assert_eq!(tokens[2].location(), (1, 16));
+        assert_eq!(tokens[3].location(), (2, 2));
+        assert_eq!(tokens[4].location(), (2, 7));
+    }
+
+    #[test]
+    fn crlf_line_endings_lex_like_lf() {
+        let source = "$funcdef entry {\n\t$set r1, 5\n\t$ret\n}\n";
+        let lf = Lexer::new(String::from(source)).tokenize().unwrap();
+        let crlf = Lexer::new(source.replace('\n', "\r\n")).tokenize().unwrap();
+        assert_eq!(crlf, lf);
+        let locations = |tokens: &[Token]| tokens.iter().map(Token::location).collect::<Vec<_>>();
+        assert_eq!(locations(&crlf), locations(&lf));
+    }
+
+    #[test]
+    fn matches_ahead_looks_past_the_current_character() {
+        let mut lexer = Lexer::new(String::from("r1 0x1F"));
+        assert!(!lexer.matches_ahead("0x"));
+        for _ in 0..3 {
+            lexer.next();
         }
+        assert!(lexer.matches_ahead("0x"));
+        assert!(lexer.matches_ahead("0x1F"));
+        assert!(!lexer.matches_ahead("0x1F0"));
+        assert!(lexer.matches_ahead(""));
+    }
+
+    #[test]
+    fn string_literals_decode_their_escapes() {
+        let tokens = Lexer::new(String::from(r#""hi\n" "a\t\"b\"\\""#)).tokenize().unwrap();
+        assert_eq!((tokens[0].ty(), tokens[0].value()), (&STRING, "hi\n"));
+        assert_eq!(tokens[0].span(), (0, 6));
+        assert_eq!(tokens[1].value(), "a\t\"b\"\\");
+        assert_eq!(Lexer::new(String::from("\"hi")).tokenize(), Err(LexError::UnterminatedString { pos: 0 }));
+        assert_eq!(Lexer::new(String::from("\"hi\\")).tokenize(), Err(LexError::UnterminatedString { pos: 0 }));
+        assert_eq!(Lexer::new(String::from(r#""\q""#)).tokenize(), Err(LexError::InvalidEscape { escape: 'q', pos: 1 }));
+    }
+
+    #[test]
+    fn large_input_tokenizes_in_linear_time() {
+        let mut lexer = Lexer::new("$nop 0x1F ident\n".repeat(20_000));
+        assert_eq!(lexer.tokenize().unwrap().len(), 60_001);
+    }
+
+    #[test]
+    fn empty_input_yields_only_eof() {
+        let tokens = Lexer::new(String::new()).tokenize().unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].to_string(), "Token with type [end of input], value [\0], pos [0]");
+    }
+
+    #[test]
+    fn registers_are_tokenized_with_their_index() {
+        let tokens = Lexer::new(String::from("r0 r31")).tokenize().unwrap();
+        assert_eq!(tokens[0].to_string(), "Token with type [r0], value [r0], pos [0]");
+        assert_eq!(tokens[1].to_string(), "Token with type [r31], value [r31], pos [3]");
+        assert_eq!(
+            Lexer::new(String::from("r99")).tokenize().unwrap_err(),
+            LexError::InvalidRegister { register: String::from("r99"), pos: 0 }
+        );
+    }
+
+    #[test]
+    fn integer_tokens_carry_their_parsed_value() {
+        let tokens = Lexer::new(String::from("0x1F 31 1.5")).tokenize().unwrap();
+        assert_eq!(tokens[0].as_u64(), Some(31));
+        assert_eq!(tokens[1].as_u64(), Some(31));
+        assert_eq!(tokens[2].as_u64(), None);
+        assert_eq!(
+            Lexer::new(String::from("0x")).tokenize().unwrap_err(),
+            LexError::MalformedNumber { number: String::from("0x"), pos: 0 }
+        );
+        assert_eq!(
+            Lexer::new(String::from("0x10000000000000000")).tokenize().unwrap_err(),
+            LexError::MalformedNumber { number: String::from("0x10000000000000000"), pos: 0 }
+        );
+    }
+
+    #[test]
+    fn minus_before_an_operand_is_a_negative_literal() {
+        let tokens = Lexer::new(String::from("-5")).tokenize().unwrap();
+        assert_eq!(tokens[0].ty(), &INTEGER { unsigned: false, size: 32 });
+        assert_eq!((tokens[0].value(), tokens[0].as_u64()), ("-5", Some(-5i64 as u64)));
+
+        let tokens = Lexer::new(String::from("1 - 2 -3")).tokenize().unwrap();
+        let types: Vec<&TokenType> = tokens.iter().map(|token| token.ty()).collect();
+        assert_eq!(types[1..5], [&MINUS, &INTEGER { unsigned: false, size: 32 }, &MINUS, &INTEGER { unsigned: false, size: 32 }]);
+
+        let tokens = Lexer::new(String::from("= -1.5f")).tokenize().unwrap();
+        assert_eq!((tokens[1].ty(), tokens[1].value()), (&FLOATING { is_double: false }, "-1.5"));
+
+        assert_eq!(Lexer::new(String::from("-128i8")).tokenize().unwrap()[0].as_u64(), Some(-128i64 as u64));
+        assert_eq!(
+            Lexer::new(String::from("-129i8")).tokenize().unwrap_err(),
+            LexError::LiteralOutOfRange { literal: String::from("-129i8"), pos: 0 }
+        );
+        assert_eq!(
+            Lexer::new(String::from("-1u8")).tokenize().unwrap_err(),
+            LexError::LiteralOutOfRange { literal: String::from("-1u8"), pos: 0 }
+        );
+    }
+
+    #[test]
+    fn integer_suffixes_set_size_and_signedness() {
+        let tokens = Lexer::new(String::from("42i64 255u8 0xFFu16 7")).tokenize().unwrap();
+        assert_eq!(tokens[0].to_string(), "Token with type [integer(i64)], value [42], pos [0]");
+        assert_eq!(tokens[0].span(), (0, 5));
+        assert_eq!(tokens[1].to_string(), "Token with type [integer(u8)], value [255], pos [6]");
+        assert_eq!(tokens[2].to_string(), "Token with type [integer(u16)], value [FF], pos [12]");
+        assert_eq!(tokens[3].to_string(), "Token with type [integer(i32)], value [7], pos [20]");
+        assert_eq!(
+            Lexer::new(String::from("300u8")).tokenize().unwrap_err(),
+            LexError::LiteralOutOfRange { literal: String::from("300u8"), pos: 0 }
+        );
+        assert_eq!(
+            Lexer::new(String::from("128i8")).tokenize().unwrap_err(),
+            LexError::LiteralOutOfRange { literal: String::from("128i8"), pos: 0 }
+        );
+        assert_eq!(
+            Lexer::new(String::from("1u12")).tokenize().unwrap_err(),
+            LexError::MalformedNumber { number: String::from("1u12"), pos: 0 }
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn invalid_input_is_reported_not_panicked() {
+        assert_eq!(
+            Lexer::new(String::from("$add #")).tokenize().unwrap_err(),
+            LexError::UnexpectedChar { chr: '#', pos: 5 }
+        );
+        assert_eq!(
+            Lexer::new(String::from("$nop $foo")).tokenize().unwrap_err(),
+            LexError::UnexpectedKeyword { keyword: String::from("$foo"), pos: 5 }
+        );
+        assert_eq!(
+            Lexer::new(String::from("1.2.3")).tokenize().unwrap_err(),
+            LexError::MalformedNumber { number: String::from("1.2."), pos: 0 }
+        );
+        assert_eq!(
+            Lexer::new(String::from("1.f")).tokenize().unwrap_err(),
+            LexError::MalformedNumber { number: String::from("1."), pos: 0 }
+        );
+    }
+}
@@ -1,2 +1,3 @@
 pub mod token;
-pub mod lexer;
\ No newline at end of file
+pub mod lexer;
+pub mod error;
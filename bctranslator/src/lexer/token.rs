@@ -1,24 +1,74 @@
 use std::fmt::{write, Display, Formatter};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TokenType {
     DOLLAR,     // $
+    NOP,        // nop
     MOVE,       // move
     ADD,        // add
     SUB,        // sub
     MUL,        // mul
     DIV,        // div
     REM,        // rem
+    SDIV,       // sdiv
+    SREM,       // srem
+    FADD,       // fadd
+    FSUB,       // fsub
+    FMUL,       // fmul
+    FDIV,       // fdiv
+    ITOF,       // itof
+    FTOI,       // ftoi
+    AND,        // and
+    OR,         // or
+    XOR,        // xor
+    NOT,        // not
+    SHL,        // shl
+    SHR,        // shr
+    SAR,        // sar
+    POPCNT,     // popcnt
+    CLZ,        // clz
+    CTZ,        // ctz
+    BSWAP,      // bswap
+    MEMCPY,     // memcpy
+    SWAP,       // swap
+    INC,        // inc
+    DEC,        // dec
+    ADDC,       // addc
+    MULC,       // mulc
+    ADC,        // adc
+    NEG,        // neg
+    ABS,        // abs
+    PUSH,       // push
+    POP,        // pop
     SET,        // set
+    LEA,        // lea
+    CMOV,       // cmov
+    CAS,        // cas
+    ADDM,       // addm
+    SUBM,       // subm
     JMP,        // jmp
     JMC,        // jmc
+    JMCI,       // jmci
+    CMP,        // cmp
+    JZ,         // jz
+    JNZ,        // jnz
+    JS,         // js
+    JNS,        // jns
+    JC,         // jc
+    JNC,        // jnc
+    JO,         // jo
+    JNO,        // jno
     FUNCDEF,    // funcdef
     PARAMS,     // params
     VARDEF,     // vardef
+    DATA,       // data
     RET,        // ret
     CALL,       // call
+    CALLR,      // callr
     HALT,       // halt
     EXIT,       // exit/quit
+    FUEL,       // fuel
+    SYSCALL,    // syscall
     I8KW,       // i8
     I16KW,      // i16
     I32KW,      // i32
@@ -29,9 +79,11 @@ pub enum TokenType {
     FLOATKW,    // float
     DOUBLEKW,   // double
     INTEGER { unsigned: bool, size: usize },
+    REGISTER { index: usize },
     ADDRESS,
     FLOATING { is_double: bool },
     IDENT,
+    STRING,
     LPAREN,     // (
     RPAREN,     // )
     LBRACE,     // {
@@ -48,23 +100,118 @@ pub enum TokenType {
     EOF
 }
 
-#[derive(Clone)]
+/// Renders the type the way it is written in source, e.g. `$add`, `$i8` or `(`.
+/// Literals render as their kind, e.g. `integer(u32)`, `floating(f64)` or `r3`.
+impl Display for TokenType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenType::DOLLAR => write!(f, "$"),
+            TokenType::I8KW => write!(f, "$i8"),
+            TokenType::I16KW => write!(f, "$i16"),
+            TokenType::I32KW => write!(f, "$i32"),
+            TokenType::I64KW => write!(f, "$i64"),
+            TokenType::UNSIGNEDKW => write!(f, "$unsigned"),
+            TokenType::FLOATKW => write!(f, "$float"),
+            TokenType::DOUBLEKW => write!(f, "$double"),
+            TokenType::AT => write!(f, "@"),
+            TokenType::EQ => write!(f, "="),
+            TokenType::INTEGER { unsigned, size } => write!(f, "integer({}{})", if *unsigned { 'u' } else { 'i' }, size),
+            TokenType::REGISTER { index } => write!(f, "r{}", index),
+            TokenType::ADDRESS => write!(f, "address"),
+            TokenType::FLOATING { is_double } => write!(f, "floating(f{})", if *is_double { 64 } else { 32 }),
+            TokenType::IDENT => write!(f, "identifier"),
+            TokenType::STRING => write!(f, "string"),
+            TokenType::LPAREN => write!(f, "("),
+            TokenType::RPAREN => write!(f, ")"),
+            TokenType::LBRACE => write!(f, "{{"),
+            TokenType::RBRACE => write!(f, "}}"),
+            TokenType::COMMA => write!(f, ","),
+            TokenType::PERIOD => write!(f, "."),
+            TokenType::UNDER => write!(f, "_"),
+            TokenType::COLON => write!(f, ":"),
+            TokenType::PLUS => write!(f, "+"),
+            TokenType::STAR => write!(f, "*"),
+            TokenType::MINUS => write!(f, "-"),
+            TokenType::SLASH => write!(f, "/"),
+            TokenType::PERCENT => write!(f, "%"),
+            TokenType::EOF => write!(f, "end of input"),
+            // Every remaining variant is an instruction or directive named after its keyword.
+            keyword => write!(f, "${}", format!("{:?}", keyword).to_lowercase())
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Token {
-    r#type: TokenType,
-    value: String,
-    pos: usize
+    pub(crate) r#type: TokenType,
+    pub(crate) value: String,
+    pub(crate) pos: usize,
+    len: usize,
+    line: usize,
+    column: usize,
+    number: Option<u64>
 }
 impl Token {
     pub fn new(r#type: TokenType, value: String, pos: usize) -> Token {
+        let len = value.chars().count();
+        Self::with_len(r#type, value, pos, len)
+    }
+    pub fn with_len(r#type: TokenType, value: String, pos: usize, len: usize) -> Token {
         Self {
             r#type,
             value,
-            pos
+            pos,
+            len,
+            line: 0,
+            column: 0,
+            number: None
         }
     }
+    /// Returns the kind of the token.
+    pub fn ty(&self) -> &TokenType {
+        &self.r#type
+    }
+    /// Returns the lexeme as written in the input.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+    /// Returns the character offset at which the lexeme starts.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+    /// Attaches the numeric value of an integer literal.
+    pub fn parsed(mut self, number: u64) -> Token {
+        self.number = Some(number);
+        self
+    }
+    /// Returns the numeric value of an integer literal, or `None` for any other token.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.number
+    }
+    /// Sets the 1-based line and column at which the lexeme starts.
+    pub fn at(mut self, line: usize, column: usize) -> Token {
+        self.line = line;
+        self.column = column;
+        self
+    }
+    /// Returns the 1-based `(line, column)` at which the lexeme starts.
+    pub fn location(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+    /// Returns the half-open `(start, end)` character range of the lexeme in the input.
+    pub fn span(&self) -> (usize, usize) {
+        (self.pos, self.pos + self.len)
+    }
+}
+/// Tokens are equal when their types and lexemes are, regardless of where they appear in the input.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.r#type == other.r#type && self.value == other.value
+    }
 }
+impl Eq for Token {}
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Token with type [{:?}], value [{}], pos [{}]", self.r#type, self.value, self.pos)
+        write!(f, "Token with type [{}], value [{}], pos [{}]", self.r#type, self.value, self.pos)
     }
 }
\ No newline at end of file
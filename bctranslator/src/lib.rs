@@ -1,25 +1,155 @@
+//! # bctranslator
+//!
+//! Translates AetherVM source into bytecode the VM can load. [translate] runs the lexer, the parser
+//! and the code generator in turn and stops at the first error, and [write_bytecode] stores the result
+//! as an image the VM's loader accepts. [check] reports every syntax error of a source at once, and [tokenize] exposes
+//! the lexer's [Token]s on their own. [encode] holds the opcode table and instruction encoders the VM shares.
 mod lexer;
 mod utils;
 mod parser;
+mod codegen;
+mod error;
 
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub use codegen::encode;
+pub use codegen::error::{CodegenError, ImageError};
+pub use error::TranslateError;
+pub use lexer::error::LexError;
+pub use lexer::token::{Token, TokenType};
+pub use parser::error::ParseError;
+
+use codegen::generator::CodeGenerator;
+use codegen::verify;
+use lexer::lexer::Lexer;
+use parser::ast::Node;
+use parser::parser::Parser;
+
+/// Magic bytes at the start of every bytecode image. The VM's loader checks them from here as well.
+pub const PROGRAM_MAGIC: &[u8; 4] = b"AVM\0";
+/// The image format version written by [write_bytecode] and understood by the VM's loader.
+pub const PROGRAM_VERSION: u8 = 2;
+
+/// The output of [translate]: the instruction bytes and the data section placed directly after them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bytecode {
+    /// Every instruction packed as 8 little-endian bytes, starting at the first `$funcdef`.
+    pub code: Vec<u8>,
+    /// The bytes of every `$data` definition, in source order.
+    pub data: Vec<u8>
+}
+
+/// Translates source code into bytecode.
+///
+/// # Returns
+/// - `Ok(Bytecode)` with the code and the data section.
+/// - `Err(TranslateError)` with the first lexer, parser or codegen error, or an [ImageError] for a function that
+///   leaves the stack unbalanced.
+pub fn translate(source: &str) -> Result<Bytecode, TranslateError> {
+    let tokens = Lexer::new(String::from(source)).tokenize()?;
+    let nodes = Parser::new(tokens).parse()?;
+    verify::stack_balance(&nodes)?;
+    let mut generator = CodeGenerator::new();
+    let code = generator.generate(&nodes)?;
+    Ok(Bytecode {
+        code: code.iter().flat_map(|word| word.to_le_bytes()).collect(),
+        data: generator.data().to_vec()
+    })
+}
+/// Splits source code into tokens, ending with an `EOF` token.
+///
+/// # Returns
+/// - `Ok(Vec<Token>)` with the tokens in source order.
+/// - `Err(LexError)` with the first lexer error.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, LexError> {
+    Lexer::new(String::from(source)).tokenize()
+}
+/// Checks source code for lexer and parser errors without generating code.
+///
+/// Unlike [translate], parsing continues after a syntax error, so every one of them is reported at once.
+///
+/// # Returns
+/// - `Ok(())` if the source lexes and parses.
+/// - `Err(Vec<TranslateError>)` with the lexer error, or with every parser error in source order.
+pub fn check(source: &str) -> Result<(), Vec<TranslateError>> {
+    let tokens = Lexer::new(String::from(source)).tokenize().map_err(|e| vec![e.into()])?;
+    Parser::new(tokens).parse_recovering()
+        .map(|_| ())
+        .map_err(|errors| errors.into_iter().map(TranslateError::from).collect())
+}
+/// Parses source code and renders the resulting syntax tree, one indented line per node.
+///
+/// Meant for inspecting how a program is parsed, e.g. which operator binds tighter.
+pub fn dump_ast(source: &str) -> Result<String, TranslateError> {
+    let tokens = Lexer::new(String::from(source)).tokenize()?;
+    let nodes = Parser::new(tokens).parse()?;
+    Ok(nodes.iter().map(Node::to_string).collect())
+}
+/// Writes translated bytecode to `path` as an image.
+///
+/// The header holds the magic, the version and the length of the code as a little-endian `u64`, so the loader can
+/// tell the code apart from the data that follows it.
+pub fn write_bytecode(path: &Path, bytecode: &Bytecode) -> io::Result<()> {
+    let mut image = Vec::with_capacity(PROGRAM_MAGIC.len() + 9 + bytecode.code.len() + bytecode.data.len());
+    image.extend_from_slice(PROGRAM_MAGIC);
+    image.push(PROGRAM_VERSION);
+    image.extend_from_slice(&(bytecode.code.len() as u64).to_le_bytes());
+    image.extend_from_slice(&bytecode.code);
+    image.extend_from_slice(&bytecode.data);
+    fs::write(path, image)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::lexer::Lexer;
-    use crate::utils::stringutils::StringBuilder;
     use super::*;
 
     #[test]
-    fn it_works() {
-        let mut input = String::from("$funcdef entry {\n\t$vardef res = 1.0f + 2.0f\n\t}");
-        let mut lexer = Lexer::new(input);
-        lexer.tokenize().iter().for_each(|x| {
-            println!("{}", x)
-        });
-
-        assert_eq!(1, 1)
+    fn translates_the_sample_program() {
+        let bytecode = translate("$funcdef entry {\n\t$vardef one = 1.0f\n\t$vardef res = one + 2.0f\n\t}").unwrap().code;
+        assert!(!bytecode.is_empty());
+        assert_eq!(bytecode.len() % 8, 0);
+        assert_eq!(&bytecode[bytecode.len() - 8..], &0xCB00_0102_0100_0000u64.to_le_bytes()); // r1 $fadd r2 -> r1
+        assert!(matches!(translate("$funcdef entry { $jmp nowhere }"), Err(TranslateError::Codegen(CodegenError::UndefinedLabel(_)))));
+    }
+
+    #[test]
+    fn tokens_are_readable_from_outside_the_lexer() {
+        let tokens = tokenize("$funcdef main").unwrap();
+        assert_eq!((tokens[0].ty(), tokens[0].value(), tokens[0].pos()), (&TokenType::FUNCDEF, "$funcdef", 0));
+        assert_eq!(tokens[1].span(), (9, 13));
+        assert_eq!(tokens.last().map(Token::ty), Some(&TokenType::EOF));
+    }
+
+    #[test]
+    fn data_follows_the_code() {
+        let bytecode = translate("$data \"hi\" $data msg, \"ok\", 0 $funcdef main { $lea r1, msg $halt }").unwrap();
+        assert_eq!(&bytecode.code[..8], &0xCD13_0100_1000_0012u64.to_le_bytes()); // r1 $lea @10000012
+        assert_eq!(bytecode.code.len(), 16);
+        assert_eq!(bytecode.data, b"hiok\0");
+        assert!(matches!(translate("$funcdef main { $lea r1, nowhere }"), Err(TranslateError::Codegen(CodegenError::UndefinedSymbol(_)))));
+    }
+
+    #[test]
+    fn functions_leaking_a_stack_slot_are_rejected() {
+        let leaked = Err(TranslateError::Image(ImageError::UnbalancedStack(String::from("leak"))));
+        assert_eq!(translate("$funcdef main { $call leak $halt } $funcdef leak { $push r1 $ret }"), leaked);
+        assert_eq!(translate("$funcdef leak { $push r1 $jmc eq r1, r2, done $pop r1 done: $ret }"), leaked);
+        assert!(translate("$funcdef main { $push r1 $jmc eq r1, r2, done $pop r2 $push r2 done: $pop r1 $ret }").is_ok());
+    }
+
+    #[test]
+    fn written_images_start_with_the_header() {
+        let bytecode = translate("$funcdef entry { $halt } $data \"hi\"").unwrap();
+        let path = std::env::temp_dir().join(format!("bctranslator-header-{}.avm", std::process::id()));
+        write_bytecode(&path, &bytecode).unwrap();
+        let image = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(&image[..4], b"AVM\0");
+        assert_eq!(image[4], PROGRAM_VERSION);
+        assert_eq!(&image[5..13], &8u64.to_le_bytes());
+        assert_eq!(&image[13..21], &bytecode.code[..]);
+        assert_eq!(&image[21..], b"hi");
     }
 }
@@ -1,35 +1,636 @@
+use std::fmt::{Display, Formatter};
 
+use crate::codegen::{encode, instruction};
+use crate::codegen::error::CodegenError;
+use crate::codegen::generator::CodeGenerator;
+use crate::codegen::regalloc::{ARGUMENT_REGISTERS, RETURN_REGISTER};
+use crate::codegen::symbols::SymbolTable;
+use crate::lexer::token::TokenType;
 
+/// Lowers an AST node into encoded instructions.
+///
+/// Expressions leave their value in [RETURN_REGISTER], so `$ret value` hands it to the caller. Their temporaries come from the generator's register allocator.
 pub trait Visitable {
-    fn accept(&mut self) -> Result<Vec<u64>, ()>;
+    fn accept(&self, gen: &mut CodeGenerator) -> Result<Vec<u64>, CodegenError>;
 }
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BinaryOperation {
-
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem
 }
 
+#[derive(Debug)]
 pub enum Node {
-    Statement(Statement),
-    Expression(Expression)
+    Statement(Statement)
 }
+#[derive(Debug)]
 pub enum Statement {
-
+    /// `$funcdef name [$params a, b] { body }`
+    FuncDef { name: String, params: Vec<String>, body: Vec<Node> },
+    /// `$vardef name [type] = value`
+    VarDef { name: String, ty: Option<Type>, value: Expression },
+    /// `$ret [value]`
+    Ret { value: Option<Expression> },
+    /// `$call name [(args)]`
+    Call { name: String, args: Vec<Expression> },
+    /// `$jmp label`
+    Jmp { label: String },
+    /// `$jmc cond rA, rB, label`
+    Jmc { cond: Condition, lhs: usize, rhs: usize, label: String },
+    /// `$jmci cond rA, imm, label`
+    Jmci { cond: Condition, lhs: usize, imm: u64, label: String },
+    /// `$cmov cond rA, rB, rSrc, rDst`
+    Cmov { cond: Condition, lhs: usize, rhs: usize, src: usize, dst: usize },
+    /// `$data [name,] "text", byte, ...`, placed in the data section after the code.
+    Data { name: Option<String>, bytes: Vec<u8> },
+    /// `label:`
+    Label(String),
+    /// Any other instruction, e.g. `$add r1, r2, r3` or `$set r1, 42`.
+    Instruction { op: TokenType, operands: Vec<Operand> }
+}
+/// The kind of value an expression produces, as far as type checking is concerned.
+///
+/// Registers are untyped, so expressions reading them directly have no [ValueType] and mix with either kind.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueType {
+    Int,
+    Float
+}
+impl Display for ValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueType::Int => write!(f, "integer"),
+            ValueType::Float => write!(f, "floating-point")
+        }
+    }
+}
+/// The declared type of a variable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Type {
+    /// `$i8`..`$i64`, optionally preceded by `$u`/`$unsigned`.
+    Int { unsigned: bool, size: usize },
+    /// `$float`
+    Float,
+    /// `$double`
+    Double
+}
+/// A comparison used by `$jmc`, `$jmci` and `$cmov`, written as its short name.
+///
+/// The `Signed*` conditions compare registers as `i64`, the `Float*` conditions as `f64` (false if either is NaN),
+/// all others as `u64`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Condition {
+    Greater,
+    Less,
+    Equal,
+    GreaterEqual,
+    LessEqual,
+    NotEqual,
+    SignedGreater,
+    SignedLess,
+    SignedGreaterEqual,
+    SignedLessEqual,
+    FloatGreater,
+    FloatLess,
+    FloatEqual,
+    FloatGreaterEqual,
+    FloatLessEqual
 }
+impl Condition {
+    /// Resolves the short name used in source (`gt`, `lt`, `eq`, `ge`, `le`, `ne`, `sgt`, `slt`, `sge`, `sle`, and
+    /// `fgt`, `flt`, `feq`, `fge`, `fle`).
+    pub fn from_name(name: &str) -> Option<Condition> {
+        match name {
+            "gt" => Some(Condition::Greater),
+            "lt" => Some(Condition::Less),
+            "eq" => Some(Condition::Equal),
+            "ge" => Some(Condition::GreaterEqual),
+            "le" => Some(Condition::LessEqual),
+            "ne" => Some(Condition::NotEqual),
+            "sgt" => Some(Condition::SignedGreater),
+            "slt" => Some(Condition::SignedLess),
+            "sge" => Some(Condition::SignedGreaterEqual),
+            "sle" => Some(Condition::SignedLessEqual),
+            "fgt" => Some(Condition::FloatGreater),
+            "flt" => Some(Condition::FloatLess),
+            "feq" => Some(Condition::FloatEqual),
+            "fge" => Some(Condition::FloatGreaterEqual),
+            "fle" => Some(Condition::FloatLessEqual),
+            _ => None
+        }
+    }
+    /// Returns the condition code `$jmc`, `$jmci` and `$cmov` expect in bits 44..48.
+    pub fn code(self) -> u64 {
+        match self {
+            Condition::Greater => 0xA,
+            Condition::Less => 0xB,
+            Condition::Equal => 0xC,
+            Condition::GreaterEqual => 0xD,
+            Condition::LessEqual => 0xE,
+            Condition::NotEqual => 0xF,
+            Condition::SignedGreater => 0x1,
+            Condition::SignedLess => 0x2,
+            Condition::SignedGreaterEqual => 0x3,
+            Condition::SignedLessEqual => 0x4,
+            Condition::FloatGreater => 0x5,
+            Condition::FloatLess => 0x6,
+            Condition::FloatEqual => 0x7,
+            Condition::FloatGreaterEqual => 0x8,
+            Condition::FloatLessEqual => 0x9
+        }
+    }
+}
+/// An operand of a bare instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    Register(usize),
+    Immediate(u64),
+    Label(String)
+}
+#[derive(Debug, PartialEq)]
 pub enum Expression {
-    Binary()
+    Binary(BinaryExpression),
+    Literal(Literal),
+    Register(usize),
+    Variable(String)
+}
+impl Expression {
+    /// Type checks the expression and returns the kind of value it produces.
+    ///
+    /// # Returns
+    /// - `Ok(Some(ValueType))` for typed expressions, `Ok(None)` for untyped ones, e.g. a register.
+    /// - `Err(CodegenError::TypeError)` if a binary operation mixes integer and floating-point operands.
+    /// - `Err(CodegenError::UndefinedSymbol)` for an unknown variable.
+    pub fn value_type(&self, symbols: &SymbolTable) -> Result<Option<ValueType>, CodegenError> {
+        match self {
+            Expression::Binary(binary) => binary.value_type(symbols),
+            Expression::Literal(Literal::Int(_)) => Ok(Some(ValueType::Int)),
+            Expression::Literal(Literal::Float { .. }) => Ok(Some(ValueType::Float)),
+            Expression::Register(_) => Ok(None),
+            Expression::Variable(name) => symbols.variable_type(name)
+        }
+    }
+}
+/// A constant operand. Floats are stored as `f64` regardless of their written precision.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Literal {
+    Int(u64),
+    Float { value: f64, is_double: bool }
 }
 impl Visitable for Node {
-    fn accept(&mut self) -> Result<Vec<u64>, ()> {
-        todo!()
+    fn accept(&self, gen: &mut CodeGenerator) -> Result<Vec<u64>, CodegenError> {
+        match self {
+            Node::Statement(statement) => statement.accept(gen)
+        }
     }
 }
+impl Visitable for Statement {
+    /// Lowers a statement. A `$vardef` keeps its value in the register it was evaluated into
+    /// until the enclosing `$funcdef` body ends, and so do parameters, see [ARGUMENT_REGISTERS].
+    fn accept(&self, gen: &mut CodeGenerator) -> Result<Vec<u64>, CodegenError> {
+        match self {
+            Statement::FuncDef { name, params, body } => {
+                gen.symbols.define_function(name, gen.offset());
+                gen.symbols.push_scope();
+                let mut code = Vec::new();
+                for (i, param) in params.iter().enumerate() {
+                    let reg = if i < ARGUMENT_REGISTERS {
+                        gen.registers.reserve(i);
+                        i
+                    } else {
+                        let reg = gen.registers.allocate()?;
+                        code.push(encode::load(reg, 64, 13, (i - ARGUMENT_REGISTERS + 1) as u64 * 8));
+                        reg
+                    };
+                    gen.symbols.define_variable(param, reg, None);
+                }
+                let start = gen.position;
+                for node in body {
+                    gen.position = start + code.len();
+                    code.extend(node.accept(gen)?);
+                }
+                for reg in gen.symbols.pop_scope() {
+                    gen.registers.release(reg);
+                }
+                Ok(code)
+            }
+            Statement::VarDef { name, ty, value } => {
+                let value_type = match (ty.map(|ty| ty.value_type()), value.value_type(&gen.symbols)?) {
+                    (Some(declared), Some(actual)) if declared != actual => {
+                        return Err(CodegenError::TypeError(format!("{} is declared {} but assigned a {} value", name, declared, actual)))
+                    }
+                    (declared, actual) => declared.or(actual)
+                };
+                let (code, reg) = value.evaluate(gen)?;
+                if let Some(previous) = gen.symbols.define_variable(name, reg, value_type) {
+                    gen.registers.release(previous);
+                }
+                Ok(code)
+            }
+            Statement::Ret { value } => {
+                // Expressions are accepted into RETURN_REGISTER, which is where the caller looks for the result
+                let mut code = match value {
+                    Some(value) => value.accept(gen)?,
+                    None => Vec::new()
+                };
+                code.push(encode::bare(encode::RET));
+                Ok(code)
+            }
+            Statement::Call { name, args } => {
+                let mut code = Vec::new();
+                // Restoring a variable must not overwrite the returned value, so it moves out of RETURN_REGISTER first
+                if gen.symbols.variable_registers().contains(&RETURN_REGISTER) {
+                    let reg = gen.registers.allocate()?;
+                    code.push(encode::binary(encode::MOVE, RETURN_REGISTER, reg));
+                    gen.symbols.relocate(RETURN_REGISTER, reg);
+                    gen.registers.release(RETURN_REGISTER);
+                }
+                let live = gen.symbols.variable_registers();
+                let mut regs = Vec::new();
+                for arg in args {
+                    let (arg_code, reg) = arg.evaluate(gen)?;
+                    code.extend(arg_code);
+                    regs.push(reg);
+                }
+                code.extend(live.iter().map(|&reg| encode::unary(encode::PUSH, reg)));
+                // Register arguments go through the stack too, so none is overwritten before it was moved
+                let in_registers = regs.len().min(ARGUMENT_REGISTERS);
+                code.extend(regs[in_registers..].iter().rev().map(|&reg| encode::unary(encode::PUSH, reg)));
+                code.extend(regs[..in_registers].iter().map(|&reg| encode::unary(encode::PUSH, reg)));
+                code.extend((0..in_registers).rev().map(|reg| encode::unary(encode::POP, reg)));
+                for &reg in &regs {
+                    gen.registers.release(reg);
+                }
+                gen.jump_to_function(code.len(), name);
+                code.push(encode::jump(encode::CALL, 0));
+                // Whatever the scratch register held is either dead or restored right after
+                let scratch = RETURN_REGISTER + 1;
+                code.extend((in_registers..regs.len()).map(|_| encode::unary(encode::POP, scratch)));
+                code.extend(live.iter().rev().map(|&reg| encode::unary(encode::POP, reg)));
+                Ok(code)
+            }
+            Statement::Jmp { label } => {
+                gen.jump_to_label(0, label);
+                Ok(vec![encode::jump(encode::JMP, 0)])
+            }
+            Statement::Jmc { cond, lhs, rhs, label } => {
+                if *lhs > 0xF || *rhs > 0xF {
+                    return Err(CodegenError::InvalidOperands(String::from("$jmc")))
+                }
+                gen.jump_to_label(0, label);
+                Ok(vec![encode::jmc(cond.code(), *lhs, *rhs, 0)])
+            }
+            Statement::Jmci { cond, lhs, imm, label } => {
+                if *lhs > 0xF {
+                    return Err(CodegenError::InvalidOperands(String::from("$jmci")))
+                }
+                if *imm > 0xFF {
+                    return Err(CodegenError::ImmediateTooLarge(*imm))
+                }
+                gen.jump_to_label(0, label);
+                Ok(vec![encode::jmci(cond.code(), *lhs, *imm, 0)])
+            }
+            Statement::Cmov { cond, lhs, rhs, src, dst } => {
+                if *lhs > 0xF || *rhs > 0xF {
+                    return Err(CodegenError::InvalidOperands(String::from("$cmov")))
+                }
+                Ok(vec![encode::cmov(cond.code(), *lhs, *rhs, *src, *dst)])
+            }
+            Statement::Data { name, bytes } => {
+                gen.define_data(name.as_deref(), bytes)?;
+                Ok(Vec::new())
+            }
+            Statement::Label(label) => {
+                gen.define_label(label)?;
+                Ok(Vec::new())
+            }
+            Statement::Instruction { op, operands } => match (instruction::flag_jump_opcode(op), operands.as_slice()) {
+                (Some(opcode), [Operand::Label(label)]) => {
+                    gen.jump_to_label(0, label);
+                    Ok(vec![encode::jump(opcode, 0)])
+                }
+                (None, [Operand::Register(reg), Operand::Label(name)]) if *op == TokenType::LEA => {
+                    gen.address_of_data(0, name);
+                    Ok(vec![encode::lea(*reg, 0)])
+                }
+                _ => instruction::lower(op, operands)
+            }
+        }
+    }
+}
+impl Visitable for Expression {
+    fn accept(&self, gen: &mut CodeGenerator) -> Result<Vec<u64>, CodegenError> {
+        let (code, reg) = self.evaluate(gen)?;
+        Ok(into_return_register(code, reg, gen))
+    }
+}
+impl Expression {
+    /// Lowers the expression into a freshly allocated register.
+    ///
+    /// # Returns
+    /// The instructions and the register holding the value, which the caller must release.
+    /// Binary operations are type checked, and those on literals only are folded into a single literal first.
+    pub fn evaluate(&self, gen: &mut CodeGenerator) -> Result<(Vec<u64>, usize), CodegenError> {
+        match self {
+            Expression::Binary(binary) => match binary.constant()? {
+                Some(literal) => Expression::Literal(literal).evaluate(gen),
+                None => binary.evaluate(gen)
+            },
+            Expression::Literal(literal) => {
+                let value = match literal {
+                    Literal::Int(value) => *value,
+                    Literal::Float { value, .. } => value.to_bits()
+                };
+                let reg = gen.registers.allocate()?;
+                if value <= encode::SET_MAX {
+                    return Ok((vec![encode::set(reg, value)], reg))
+                }
+                let scratch = gen.registers.allocate()?;
+                gen.registers.release(scratch);
+                Ok((encode::load_const(reg, scratch, value), reg))
+            }
+            Expression::Register(index) => {
+                let reg = gen.registers.allocate()?;
+                Ok((vec![encode::binary(encode::MOVE, *index, reg)], reg))
+            }
+            Expression::Variable(name) => {
+                let index = gen.symbols.variable(name)?;
+                let reg = gen.registers.allocate()?;
+                Ok((vec![encode::binary(encode::MOVE, index, reg)], reg))
+            }
+        }
+    }
+}
+#[derive(Debug, PartialEq)]
 pub struct BinaryExpression {
-    left: Node,
-    op: BinaryOperation,
-    right: Node
+    pub left: Box<Expression>,
+    pub op: BinaryOperation,
+    pub right: Box<Expression>
 }
 impl Visitable for BinaryExpression {
-    fn accept(&mut self) -> Result<Vec<u64>, ()> {
-        let mut l_result = self.left.accept()?;
-        let mut r_result = self.right.accept()?;
+    fn accept(&self, gen: &mut CodeGenerator) -> Result<Vec<u64>, CodegenError> {
+        let (code, reg) = self.evaluate(gen)?;
+        Ok(into_return_register(code, reg, gen))
+    }
+}
+impl BinaryExpression {
+    /// Type checks both operands, see [Expression::value_type].
+    ///
+    /// An untyped operand takes the type of the other one.
+    pub fn value_type(&self, symbols: &SymbolTable) -> Result<Option<ValueType>, CodegenError> {
+        match (self.left.value_type(symbols)?, self.right.value_type(symbols)?) {
+            (Some(left), Some(right)) if left != right => Err(self.mismatch(left, right)),
+            (left, right) => Ok(left.or(right))
+        }
+    }
+    fn mismatch(&self, left: ValueType, right: ValueType) -> CodegenError {
+        CodegenError::TypeError(format!("{:?} of {} and {} values needs a conversion", self.op, left, right))
+    }
+    /// Computes the value of the operation if both operands are literals or foldable themselves.
+    ///
+    /// Folding follows the VM: integers wrap and divide as `u64`, floats follow IEEE 754.
+    ///
+    /// # Returns
+    /// - `Ok(Some(Literal))` with the folded value.
+    /// - `Ok(None)` if an operand refers to a register or variable.
+    /// - `Err(CodegenError::DivisionByZero)` for an integer division or remainder by zero.
+    /// - `Err(CodegenError::TypeError)` if an integer and a float are mixed.
+    /// - `Err(CodegenError::Unsupported)` for a floating-point remainder.
+    pub fn constant(&self) -> Result<Option<Literal>, CodegenError> {
+        let operand = |expression: &Expression| match expression {
+            Expression::Literal(literal) => Ok(Some(*literal)),
+            Expression::Binary(binary) => binary.constant(),
+            Expression::Register(_) | Expression::Variable(_) => Ok(None)
+        };
+        let (Some(left), Some(right)) = (operand(&self.left)?, operand(&self.right)?) else {
+            return Ok(None)
+        };
+        let folded = match (left, right) {
+            (Literal::Int(a), Literal::Int(b)) => Literal::Int(match self.op {
+                BinaryOperation::Add => a.wrapping_add(b),
+                BinaryOperation::Sub => a.wrapping_sub(b),
+                BinaryOperation::Mul => a.wrapping_mul(b),
+                BinaryOperation::Div => a.checked_div(b).ok_or(CodegenError::DivisionByZero)?,
+                BinaryOperation::Rem => a.checked_rem(b).ok_or(CodegenError::DivisionByZero)?
+            }),
+            (Literal::Float { value: a, is_double: left_double }, Literal::Float { value: b, is_double: right_double }) => {
+                let value = match self.op {
+                    BinaryOperation::Add => a + b,
+                    BinaryOperation::Sub => a - b,
+                    BinaryOperation::Mul => a * b,
+                    BinaryOperation::Div => a / b,
+                    BinaryOperation::Rem => return Err(CodegenError::Unsupported(String::from("floating-point remainder")))
+                };
+                Literal::Float { value, is_double: left_double || right_double }
+            }
+            (Literal::Int(_), Literal::Float { .. }) => return Err(self.mismatch(ValueType::Int, ValueType::Float)),
+            (Literal::Float { .. }, Literal::Int(_)) => return Err(self.mismatch(ValueType::Float, ValueType::Int))
+        };
+        Ok(Some(folded))
+    }
+    /// Evaluates both operands into temporaries and combines them into the left one,
+    /// releasing the right one afterwards.
+    pub fn evaluate(&self, gen: &mut CodeGenerator) -> Result<(Vec<u64>, usize), CodegenError> {
+        let float = self.value_type(&gen.symbols)? == Some(ValueType::Float);
+        let (mut code, left) = self.left.evaluate(gen)?;
+        let (right_code, right) = self.right.evaluate(gen)?;
+        code.extend(right_code);
+        let opcode = match (self.op, float) {
+            (BinaryOperation::Add, false) => encode::ADD,
+            (BinaryOperation::Sub, false) => encode::SUB,
+            (BinaryOperation::Mul, false) => encode::MUL,
+            (BinaryOperation::Div, false) => encode::DIV,
+            (BinaryOperation::Add, true) => encode::FADD,
+            (BinaryOperation::Sub, true) => encode::FSUB,
+            (BinaryOperation::Mul, true) => encode::FMUL,
+            (BinaryOperation::Div, true) => encode::FDIV,
+            (BinaryOperation::Rem, false) => {
+                // a % b == a - (a / b) * b
+                let quotient = gen.registers.allocate()?;
+                code.extend([
+                    encode::ternary(encode::DIV, left, right, quotient),
+                    encode::ternary(encode::MUL, quotient, right, quotient),
+                    encode::ternary(encode::SUB, left, quotient, left)
+                ]);
+                gen.registers.release(quotient);
+                gen.registers.release(right);
+                return Ok((code, left))
+            }
+            (BinaryOperation::Rem, true) => return Err(CodegenError::Unsupported(String::from("floating-point remainder")))
+        };
+        code.push(encode::ternary(opcode, left, right, left));
+        gen.registers.release(right);
+        Ok((code, left))
+    }
+}
+/// Renders the node as an indented tree, one line per node, with children two spaces deeper than their parent.
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        self.write_tree(0, &mut out);
+        f.write_str(&out)
+    }
+}
+/// Renders the statement as an indented tree, like [Node] does.
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        self.write_tree(0, &mut out);
+        f.write_str(&out)
+    }
+}
+/// Renders the expression as an indented tree, like [Node] does.
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        self.write_tree(0, &mut out);
+        f.write_str(&out)
+    }
+}
+impl Node {
+    fn write_tree(&self, depth: usize, out: &mut String) {
+        match self {
+            Node::Statement(statement) => statement.write_tree(depth, out)
+        }
+    }
+}
+impl Statement {
+    fn write_tree(&self, depth: usize, out: &mut String) {
+        let line = match self {
+            Statement::FuncDef { name, params, .. } if params.is_empty() => format!("FuncDef {}", name),
+            Statement::FuncDef { name, params, .. } => format!("FuncDef {} ({})", name, params.join(", ")),
+            Statement::VarDef { name, ty: Some(ty), .. } => format!("VarDef {} {}", name, ty.name()),
+            Statement::VarDef { name, ty: None, .. } => format!("VarDef {}", name),
+            Statement::Ret { .. } => String::from("Ret"),
+            Statement::Call { name, .. } => format!("Call {}", name),
+            Statement::Jmp { label } => format!("Jmp {}", label),
+            Statement::Jmc { cond, lhs, rhs, label } => format!("Jmc {:?} r{}, r{}, {}", cond, lhs, rhs, label),
+            Statement::Jmci { cond, lhs, imm, label } => format!("Jmci {:?} r{}, {}, {}", cond, lhs, imm, label),
+            Statement::Cmov { cond, lhs, rhs, src, dst } => format!("Cmov {:?} r{}, r{}, r{}, r{}", cond, lhs, rhs, src, dst),
+            Statement::Data { name: Some(name), bytes } => format!("Data {} ({} bytes)", name, bytes.len()),
+            Statement::Data { name: None, bytes } => format!("Data ({} bytes)", bytes.len()),
+            Statement::Label(label) => format!("Label {}", label),
+            Statement::Instruction { op, operands } => {
+                let operands: Vec<String> = operands.iter().map(|operand| match operand {
+                    Operand::Register(index) => format!("r{}", index),
+                    Operand::Immediate(value) => value.to_string(),
+                    Operand::Label(label) => label.clone()
+                }).collect();
+                format!("Instruction {} {}", instruction::mnemonic(op), operands.join(", ")).trim_end().to_string()
+            }
+        };
+        out.push_str(&format!("{}{}\n", "  ".repeat(depth), line));
+        match self {
+            Statement::FuncDef { body, .. } => body.iter().for_each(|node| node.write_tree(depth + 1, out)),
+            Statement::VarDef { value, .. } | Statement::Ret { value: Some(value) } => value.write_tree(depth + 1, out),
+            Statement::Call { args, .. } => args.iter().for_each(|arg| arg.write_tree(depth + 1, out)),
+            _ => {}
+        }
+    }
+}
+impl Expression {
+    fn write_tree(&self, depth: usize, out: &mut String) {
+        let line = match self {
+            Expression::Binary(binary) => format!("Binary {:?}", binary.op),
+            Expression::Literal(Literal::Int(value)) => format!("Literal {}", value),
+            Expression::Literal(Literal::Float { value, is_double: false }) => format!("Literal {:?}f", value),
+            Expression::Literal(Literal::Float { value, is_double: true }) => format!("Literal {:?}", value),
+            Expression::Register(index) => format!("Register r{}", index),
+            Expression::Variable(name) => format!("Variable {}", name)
+        };
+        out.push_str(&format!("{}{}\n", "  ".repeat(depth), line));
+        if let Expression::Binary(binary) = self {
+            binary.left.write_tree(depth + 1, out);
+            binary.right.write_tree(depth + 1, out);
+        }
+    }
+}
+impl Type {
+    /// Returns the kind of value a variable of this type holds.
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Type::Int { .. } => ValueType::Int,
+            Type::Float | Type::Double => ValueType::Float
+        }
+    }
+    /// Returns the type as written in source, e.g. `$u $i16`.
+    fn name(&self) -> String {
+        match self {
+            Type::Int { unsigned: true, size } => format!("$u $i{}", size),
+            Type::Int { unsigned: false, size } => format!("$i{}", size),
+            Type::Float => String::from("$float"),
+            Type::Double => String::from("$double")
+        }
+    }
+}
+/// Moves an evaluated value into [RETURN_REGISTER] and releases its temporary.
+fn into_return_register(mut code: Vec<u64>, reg: usize, gen: &mut CodeGenerator) -> Vec<u64> {
+    if reg != RETURN_REGISTER {
+        code.push(encode::binary(encode::MOVE, reg, RETURN_REGISTER));
+    }
+    gen.registers.release(reg);
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::regalloc::LAST_ALLOCATABLE;
+
+    #[test]
+    fn vardef_holds_its_operands() {
+        let node = Statement::VarDef {
+            name: String::from("res"),
+            ty: Some(Type::Int { unsigned: true, size: 64 }),
+            value: Expression::Literal(Literal::Int(3))
+        };
+        let Statement::VarDef { name, ty, value } = node else { panic!("expected a vardef") };
+        assert_eq!(name, "res");
+        assert_eq!(ty, Some(Type::Int { unsigned: true, size: 64 }));
+        assert_eq!(value, Expression::Literal(Literal::Int(3)));
+    }
+
+    fn binary(left: Expression, op: BinaryOperation, right: Expression) -> Expression {
+        Expression::Binary(BinaryExpression { left: Box::new(left), op, right: Box::new(right) })
+    }
+
+    #[test]
+    fn binary_expression_lowers_into_temporaries() {
+        let expression = BinaryExpression {
+            left: Box::new(Expression::Literal(Literal::Int(1))),
+            op: BinaryOperation::Add,
+            right: Box::new(Expression::Literal(Literal::Int(2)))
+        };
+        assert_eq!(expression.accept(&mut CodeGenerator::new()), Ok(vec![
+            0xCD01_0000_0000_0001, // r0 $set 1
+            0xCD01_0100_0000_0002, // r1 $set 2
+            0xCD02_0001_0000_0000, // r0 $add r1 -> r0
+        ]));
+    }
+
+    #[test]
+    fn nested_expressions_release_their_temporaries() {
+        let mut gen = CodeGenerator::new();
+        let product = binary(
+            binary(Expression::Register(0), BinaryOperation::Add, Expression::Register(0)),
+            BinaryOperation::Mul,
+            binary(Expression::Register(0), BinaryOperation::Rem, Expression::Register(0))
+        );
+        let (code, reg) = product.evaluate(&mut gen).unwrap();
+        assert_eq!(reg, 0);
+        assert_eq!(code.last(), Some(&0xCD04_0001_0000_0000)); // r0 $mul r1 -> r0
+        let highest = code.iter().map(|word| (word >> 40) & 0xFF).max();
+        assert_eq!(highest, Some(3));
+        gen.registers.release(reg);
+        assert!((0..=LAST_ALLOCATABLE).all(|_| gen.registers.allocate().is_ok()));
+
+        let mut deep = Expression::Register(0);
+        for _ in 0..12 {
+            deep = binary(Expression::Register(0), BinaryOperation::Add, deep);
+        }
+        assert_eq!(deep.evaluate(&mut CodeGenerator::new()), Err(CodegenError::RegistersExhausted));
     }
 }
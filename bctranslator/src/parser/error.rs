@@ -0,0 +1,25 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// An error produced while building the AST from tokens.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// A token that does not fit the grammar at its position, spanning the characters `pos..end`.
+    UnexpectedToken { found: String, expected: &'static str, pos: usize, end: usize }
+}
+impl ParseError {
+    /// Returns the half-open `(start, end)` character range of the offending token, for underlining it.
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            ParseError::UnexpectedToken { pos, end, .. } => (*pos, *end)
+        }
+    }
+}
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedToken { found, expected, pos, .. } => write!(f, "Unexpected {} at pos {}, expected {}", found.escape_default(), pos, expected)
+        }
+    }
+}
+impl Error for ParseError {}
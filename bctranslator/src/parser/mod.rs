@@ -1 +1,3 @@
-mod ast;
\ No newline at end of file
+pub mod ast;
+pub mod parser;
+pub mod error;
@@ -0,0 +1,569 @@
+use crate::lexer::token::{Token, TokenType};
+use crate::lexer::token::TokenType::{CALL, CMOV, COLON, COMMA, DATA, DOUBLEKW, EOF, EQ, FLOATING, FLOATKW, FUNCDEF, I16KW, I32KW, I64KW, I8KW, IDENT, INTEGER, JMC, JMCI, JMP, LBRACE, LPAREN, MINUS, PARAMS, PERCENT, PLUS, RBRACE, REGISTER, RET, RPAREN, SLASH, STAR, STRING, UNSIGNEDKW, VARDEF};
+use crate::parser::ast::{BinaryExpression, BinaryOperation, Condition, Expression, Literal, Node, Operand, Statement, Type};
+use crate::parser::error::ParseError;
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// The errors skipped so far when parsing with [Parser::parse_recovering], `None` otherwise.
+    recovered: Option<Vec<ParseError>>
+}
+
+impl Parser {
+    /// Creates a parser over the tokens produced by [Lexer::tokenize](crate::lexer::lexer::Lexer::tokenize).
+    ///
+    /// The token list is expected to end with an EOF token.
+    pub fn new(tokens: Vec<Token>) -> Parser {
+        Self {
+            tokens,
+            pos: 0,
+            recovered: None
+        }
+    }
+    /// Parses the whole token list into a list of top-level nodes: functions and `$data` definitions.
+    pub fn parse(&mut self) -> Result<Vec<Node>, ParseError> {
+        let mut nodes = Vec::new();
+        while !self.check(EOF) {
+            let start = self.pos;
+            match if self.check(DATA) { self.parse_data() } else { self.parse_funcdef() } {
+                Ok(statement) => nodes.push(Node::Statement(statement)),
+                Err(e) => {
+                    self.recover(e)?;
+                    self.skip_past(start, |parser| matches!(parser.cur().r#type, FUNCDEF | DATA));
+                }
+            }
+        }
+        Ok(nodes)
+    }
+    /// Parses like [Parser::parse], but keeps going after a syntax error.
+    ///
+    /// A statement with an error is skipped up to the end of its line or the closing `}` of its function, and a
+    /// malformed function or `$data` header up to the next `$funcdef` or `$data`.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<Node>)` if the tokens parsed without errors.
+    /// - `Err(Vec<ParseError>)` with every error found, in source order.
+    pub fn parse_recovering(&mut self) -> Result<Vec<Node>, Vec<ParseError>> {
+        self.recovered = Some(Vec::new());
+        let result = self.parse();
+        let mut errors = self.recovered.take().unwrap_or_default();
+        match result {
+            Ok(nodes) if errors.is_empty() => Ok(nodes),
+            Ok(_) => Err(errors),
+            Err(e) => {
+                errors.push(e);
+                Err(errors)
+            }
+        }
+    }
+    /// Parses `$data [name,] item, ...`, where every item is a string literal or an integer that fits into a byte.
+    fn parse_data(&mut self) -> Result<Statement, ParseError> {
+        self.expect(DATA, "$data")?;
+        let name = if self.check(IDENT) {
+            let name = self.expect(IDENT, "data name")?.value;
+            self.expect(COMMA, ",")?;
+            Some(name)
+        } else {
+            None
+        };
+        let mut bytes = Vec::new();
+        loop {
+            match self.cur().r#type {
+                STRING => bytes.extend_from_slice(self.cur().value.as_bytes()),
+                INTEGER { .. } => match self.cur().as_u64().and_then(|value| u8::try_from(value).ok()) {
+                    Some(byte) => bytes.push(byte),
+                    None => return Err(self.unexpected("byte"))
+                },
+                _ => return Err(self.unexpected("string or byte"))
+            }
+            self.next();
+            if !self.check(COMMA) {
+                return Ok(Statement::Data { name, bytes })
+            }
+            self.next();
+        }
+    }
+    fn parse_funcdef(&mut self) -> Result<Statement, ParseError> {
+        self.expect(FUNCDEF, "$funcdef")?;
+        let name = self.expect(IDENT, "function name")?.value;
+        let mut params = Vec::new();
+        if self.check(PARAMS) {
+            self.next();
+            params.push(self.expect(IDENT, "parameter name")?.value);
+            while self.check(COMMA) {
+                self.next();
+                params.push(self.expect(IDENT, "parameter name")?.value);
+            }
+        }
+        self.expect(LBRACE, "{")?;
+        let mut body = Vec::new();
+        while !self.check(RBRACE) {
+            let start = self.pos;
+            match self.parse_statement() {
+                Ok(statement) => body.push(Node::Statement(statement)),
+                Err(e) => {
+                    self.recover(e)?;
+                    let line = self.tokens[start].location().0;
+                    self.skip_past(start, |parser| parser.check(RBRACE) || parser.cur().location().0 != line);
+                }
+            }
+        }
+        self.next();
+        Ok(Statement::FuncDef { name, params, body })
+    }
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        match self.cur().r#type {
+            VARDEF => {
+                self.next();
+                let name = self.expect(IDENT, "variable name")?.value;
+                let ty = self.parse_type()?;
+                self.expect(EQ, "=")?;
+                Ok(Statement::VarDef { name, ty, value: self.parse_expression()? })
+            }
+            RET => {
+                self.next();
+                let value = if self.at_statement_end() { None } else { Some(self.parse_expression()?) };
+                Ok(Statement::Ret { value })
+            }
+            CALL => {
+                self.next();
+                let name = self.expect(IDENT, "function name")?.value;
+                let mut args = Vec::new();
+                if self.check(LPAREN) {
+                    self.next();
+                    while !self.check(RPAREN) {
+                        if !args.is_empty() {
+                            self.expect(COMMA, ",")?;
+                        }
+                        args.push(self.parse_expression()?);
+                    }
+                    self.next();
+                }
+                Ok(Statement::Call { name, args })
+            }
+            JMP => {
+                self.next();
+                Ok(Statement::Jmp { label: self.expect(IDENT, "label")?.value })
+            }
+            JMC => {
+                self.next();
+                let cond = self.parse_condition()?;
+                let lhs = self.parse_register()?;
+                self.expect(COMMA, ",")?;
+                let rhs = self.parse_register()?;
+                self.expect(COMMA, ",")?;
+                let label = self.expect(IDENT, "label")?.value;
+                Ok(Statement::Jmc { cond, lhs, rhs, label })
+            }
+            JMCI => {
+                self.next();
+                let cond = self.parse_condition()?;
+                let lhs = self.parse_register()?;
+                self.expect(COMMA, ",")?;
+                let Some(imm) = self.cur().as_u64() else {
+                    return Err(self.unexpected("integer"))
+                };
+                self.next();
+                self.expect(COMMA, ",")?;
+                let label = self.expect(IDENT, "label")?.value;
+                Ok(Statement::Jmci { cond, lhs, imm, label })
+            }
+            CMOV => {
+                self.next();
+                let cond = self.parse_condition()?;
+                let lhs = self.parse_register()?;
+                self.expect(COMMA, ",")?;
+                let rhs = self.parse_register()?;
+                self.expect(COMMA, ",")?;
+                let src = self.parse_register()?;
+                self.expect(COMMA, ",")?;
+                let dst = self.parse_register()?;
+                Ok(Statement::Cmov { cond, lhs, rhs, src, dst })
+            }
+            IDENT if self.peek(1).r#type == COLON => {
+                let label = self.cur().value.clone();
+                self.next();
+                self.next();
+                Ok(Statement::Label(label))
+            }
+            FUNCDEF | PARAMS | I8KW | I16KW | I32KW | I64KW | UNSIGNEDKW | FLOATKW | DOUBLEKW => Err(self.unexpected("instruction")),
+            _ if self.is_keyword() => {
+                let op = self.cur().r#type.clone();
+                self.next();
+                Ok(Statement::Instruction { op, operands: self.parse_operands()? })
+            }
+            _ => Err(self.unexpected("instruction"))
+        }
+    }
+    /// Parses an optional variable type: `$i8`..`$i64` (optionally after `$u`), `$float` or `$double`.
+    fn parse_type(&mut self) -> Result<Option<Type>, ParseError> {
+        let unsigned = self.check(UNSIGNEDKW);
+        if unsigned {
+            self.next();
+        }
+        let ty = match self.cur().r#type {
+            I8KW => Type::Int { unsigned, size: 8 },
+            I16KW => Type::Int { unsigned, size: 16 },
+            I32KW => Type::Int { unsigned, size: 32 },
+            I64KW => Type::Int { unsigned, size: 64 },
+            FLOATKW if !unsigned => Type::Float,
+            DOUBLEKW if !unsigned => Type::Double,
+            _ if unsigned => return Err(self.unexpected("integer type")),
+            _ => return Ok(None)
+        };
+        self.next();
+        Ok(Some(ty))
+    }
+    fn parse_operands(&mut self) -> Result<Vec<Operand>, ParseError> {
+        let mut operands = Vec::new();
+        if self.at_statement_end() {
+            return Ok(operands)
+        }
+        loop {
+            let token = self.cur().clone();
+            operands.push(match token.r#type {
+                REGISTER { index } => Operand::Register(index),
+                INTEGER { .. } => Operand::Immediate(token.as_u64().unwrap_or_default()),
+                IDENT => Operand::Label(token.value),
+                _ => return Err(self.unexpected("operand"))
+            });
+            self.next();
+            if !self.check(COMMA) {
+                return Ok(operands)
+            }
+            self.next();
+        }
+    }
+    fn parse_condition(&mut self) -> Result<Condition, ParseError> {
+        let Some(cond) = Condition::from_name(&self.cur().value) else {
+            return Err(self.unexpected("condition"))
+        };
+        self.next();
+        Ok(cond)
+    }
+    fn parse_register(&mut self) -> Result<usize, ParseError> {
+        match self.cur().r#type {
+            REGISTER { index } => {
+                self.next();
+                Ok(index)
+            }
+            _ => Err(self.unexpected("register"))
+        }
+    }
+    /// Parses a sum of terms, so that `*`, `/` and `%` bind tighter than `+` and `-`.
+    fn parse_expression(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_term()?;
+        loop {
+            let op = match self.cur().r#type {
+                PLUS => BinaryOperation::Add,
+                MINUS => BinaryOperation::Sub,
+                _ => return Ok(left)
+            };
+            self.next();
+            let right = self.parse_term()?;
+            left = Expression::Binary(BinaryExpression { left: Box::new(left), op, right: Box::new(right) });
+        }
+    }
+    fn parse_term(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_factor()?;
+        loop {
+            let op = match self.cur().r#type {
+                STAR => BinaryOperation::Mul,
+                SLASH => BinaryOperation::Div,
+                PERCENT => BinaryOperation::Rem,
+                _ => return Ok(left)
+            };
+            self.next();
+            let right = self.parse_factor()?;
+            left = Expression::Binary(BinaryExpression { left: Box::new(left), op, right: Box::new(right) });
+        }
+    }
+    fn parse_factor(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur().clone();
+        let expression = match token.r#type {
+            INTEGER { .. } => Expression::Literal(Literal::Int(token.as_u64().unwrap_or_default())),
+            FLOATING { is_double } => match token.value.parse::<f64>() {
+                Ok(value) => Expression::Literal(Literal::Float { value, is_double }),
+                Err(_) => return Err(self.unexpected("number"))
+            },
+            REGISTER { index } => Expression::Register(index),
+            IDENT => Expression::Variable(token.value),
+            LPAREN => {
+                self.next();
+                let inner = self.parse_expression()?;
+                if !self.check(RPAREN) {
+                    return Err(self.unexpected(")"))
+                }
+                inner
+            }
+            _ => return Err(self.unexpected("expression"))
+        };
+        self.next();
+        Ok(expression)
+    }
+    /// Checks whether the current token ends a statement: a keyword, a label, the end of the block or EOF.
+    fn at_statement_end(&self) -> bool {
+        self.is_keyword()
+            || matches!(self.cur().r#type, RBRACE | EOF)
+            || (self.check(IDENT) && self.peek(1).r#type == COLON)
+    }
+    /// Records `error` when parsing with [Parser::parse_recovering], or returns it otherwise.
+    ///
+    /// Errors at the end of input are always returned, since there is nothing left to recover with.
+    fn recover(&mut self, error: ParseError) -> Result<(), ParseError> {
+        let at_end = self.check(EOF);
+        match self.recovered.as_mut() {
+            Some(errors) if !at_end => {
+                errors.push(error);
+                Ok(())
+            }
+            _ => Err(error)
+        }
+    }
+    /// Skips tokens until `boundary` holds or the input ends, consuming at least the token at `start`.
+    fn skip_past(&mut self, start: usize, boundary: impl Fn(&Parser) -> bool) {
+        if self.pos == start {
+            self.next();
+        }
+        while !self.check(EOF) && !boundary(self) {
+            self.next();
+        }
+    }
+    /// Consumes the current token if it has type `ty`.
+    fn expect(&mut self, ty: TokenType, expected: &'static str) -> Result<Token, ParseError> {
+        if self.check(ty) {
+            let token = self.cur().clone();
+            self.next();
+            Ok(token)
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+    fn check(&self, ty: TokenType) -> bool {
+        self.cur().r#type == ty
+    }
+    fn unexpected(&self, expected: &'static str) -> ParseError {
+        let token = self.cur();
+        let (pos, end) = token.span();
+        ParseError::UnexpectedToken { found: token.value.clone(), expected, pos, end }
+    }
+    fn is_keyword(&self) -> bool {
+        self.cur().value.starts_with('$') && !self.check(STRING)
+    }
+    fn cur(&self) -> &Token {
+        self.peek(0)
+    }
+    fn peek(&self, offset: usize) -> &Token {
+        &self.tokens[(self.pos + offset).min(self.tokens.len() - 1)]
+    }
+    fn next(&mut self) {
+        self.pos += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::encode;
+    use crate::codegen::error::CodegenError;
+    use crate::codegen::generator::CodeGenerator;
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::ast::Visitable;
+
+    fn parse(input: &str) -> Result<Vec<Node>, ParseError> {
+        Parser::new(Lexer::new(String::from(input)).tokenize().unwrap()).parse()
+    }
+
+    #[test]
+    fn parses_funcdef_with_its_statements() {
+        let nodes = parse("$funcdef entry {\n\t$vardef res = 1.0f + 2.0f\n\t}").unwrap();
+        assert_eq!(nodes.len(), 1);
+        let Node::Statement(Statement::FuncDef { name, params, body }) = &nodes[0] else { panic!("expected a funcdef") };
+        assert_eq!(name, "entry");
+        assert!(params.is_empty());
+        assert_eq!(body.len(), 1);
+        let Node::Statement(Statement::VarDef { name, ty, value }) = &body[0] else { panic!("expected a vardef") };
+        assert_eq!(name, "res");
+        assert_eq!(*ty, None);
+        assert!(matches!(value, Expression::Binary(BinaryExpression { op: BinaryOperation::Add, .. })));
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        let nodes = parse("$funcdef main { $vardef x = 1 + 2 * (r3 - y) }").unwrap();
+        let Node::Statement(Statement::FuncDef { body, .. }) = &nodes[0] else { panic!("expected a funcdef") };
+        let Node::Statement(Statement::VarDef { value, .. }) = &body[0] else { panic!("expected a vardef") };
+        let binary = |left, op, right| Expression::Binary(BinaryExpression { left: Box::new(left), op, right: Box::new(right) });
+        assert_eq!(*value, binary(
+            Expression::Literal(Literal::Int(1)),
+            BinaryOperation::Add,
+            binary(
+                Expression::Literal(Literal::Int(2)),
+                BinaryOperation::Mul,
+                binary(Expression::Register(3), BinaryOperation::Sub, Expression::Variable(String::from("y")))
+            )
+        ));
+
+        let nodes = parse("$funcdef main { $vardef x = 1 + 2 * r3 }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Ok(vec![
+            0xCD01_0000_0000_0001, // r0 $set 1
+            0xCD01_0100_0000_0002, // r1 $set 2
+            0xCD00_0302_0000_0000, // r3 $move r2
+            0xCD04_0102_0100_0000, // r1 $mul r2 -> r1
+            0xCD02_0001_0000_0000, // r0 $add r1 -> r0
+        ]));
+    }
+
+    #[test]
+    fn pretty_printed_trees_show_precedence() {
+        let nodes = parse("$funcdef main { $vardef x = 1 + 2 * 3 $ret }").unwrap();
+        assert_eq!(nodes[0].to_string(), "\
+FuncDef main
+  VarDef x
+    Binary Add
+      Literal 1
+      Binary Mul
+        Literal 2
+        Literal 3
+  Ret
+");
+    }
+
+    #[test]
+    fn parses_each_statement_kind() {
+        let nodes = parse("$funcdef main {
+            $vardef n $u $i16 = 5
+            loop:
+            $add r1, r2, r3
+            $jmc ne r1, r2, loop
+            $call helper
+            $jmp loop
+            $ret
+        }").unwrap();
+        let Node::Statement(Statement::FuncDef { body, .. }) = &nodes[0] else { panic!("expected a funcdef") };
+        let statements: Vec<&Statement> = body.iter().map(|Node::Statement(statement)| statement).collect();
+        assert!(matches!(statements[0], Statement::VarDef { ty: Some(Type::Int { unsigned: true, size: 16 }), .. }));
+        assert!(matches!(statements[1], Statement::Label(label) if label == "loop"));
+        assert!(matches!(statements[2], Statement::Instruction { op: TokenType::ADD, operands }
+            if *operands == vec![Operand::Register(1), Operand::Register(2), Operand::Register(3)]));
+        assert!(matches!(statements[3], Statement::Jmc { cond: Condition::NotEqual, lhs: 1, rhs: 2, label } if label == "loop"));
+        assert!(matches!(statements[4], Statement::Call { name, args } if name == "helper" && args.is_empty()));
+        assert!(matches!(statements[5], Statement::Jmp { label } if label == "loop"));
+        assert!(matches!(statements[6], Statement::Ret { value: None }));
+    }
+
+    #[test]
+    fn vardef_compiles_to_an_add() {
+        let nodes = parse("$funcdef main { $vardef res = r1 + 2 }").unwrap();
+        let code = nodes[0].accept(&mut CodeGenerator::new()).unwrap();
+        assert_eq!(code.last().map(|word| word >> 48), Some(encode::ADD));
+        assert_eq!(code.last().map(|word| (word >> 24) & 0xFF_FFFF), Some(0x00_01_00)); // r0 $add r1 -> r0
+    }
+
+    #[test]
+    fn constant_expressions_are_folded() {
+        let nodes = parse("$funcdef main { $vardef x = 2 + 3 * 4 }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Ok(vec![0xCD01_0000_0000_000E])); // r0 $set 14
+        let nodes = parse("$funcdef main { $vardef x = 1.5f * 2.0f }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), CodeGenerator::new().generate(&parse("$funcdef main { $vardef x = 3.0f }").unwrap()));
+        let nodes = parse("$funcdef main { $vardef x = 7 / (2 - 2) }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Err(CodegenError::DivisionByZero));
+        let nodes = parse("$funcdef main { $vardef x = r1 / 0 }").unwrap();
+        assert!(nodes[0].accept(&mut CodeGenerator::new()).is_ok());
+    }
+
+    #[test]
+    fn mixing_ints_and_floats_is_a_type_error() {
+        let type_error = |source: &str| matches!(parse(source).unwrap()[0].accept(&mut CodeGenerator::new()), Err(CodegenError::TypeError(_)));
+        assert!(type_error("$funcdef main { $vardef x = 1 + 1.5f }"));
+        assert!(type_error("$funcdef main { $vardef f = 1.5f $vardef g = f + 1 }"));
+        assert!(type_error("$funcdef main { $vardef x $float = 1 }"));
+        assert!(!type_error("$funcdef main { $vardef f = 1.5f $vardef g = f + 2.0f $vardef h = r1 + g }"));
+    }
+
+    #[test]
+    fn variables_are_resolved_in_later_expressions() {
+        let nodes = parse("$funcdef main { $vardef a = 2 $vardef b = a * 3 }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Ok(vec![
+            0xCD01_0000_0000_0002, // r0 $set 2
+            0xCD00_0001_0000_0000, // r0 $move r1
+            0xCD01_0200_0000_0003, // r2 $set 3
+            0xCD04_0102_0100_0000, // r1 $mul r2 -> r1
+        ]));
+        let nodes = parse("$funcdef main { $vardef a = b }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Err(CodegenError::UndefinedSymbol(String::from("b"))));
+    }
+
+    #[test]
+    fn forward_jumps_are_backpatched() {
+        let nodes = parse("$funcdef main { $jmc eq r1, r2, done $jmp main_loop main_loop: $nop done: $halt }").unwrap();
+        assert_eq!(CodeGenerator::new().generate(&nodes), Ok(vec![
+            0xCF01_C120_1000_0018, // $jmc eq r1, r2 -> done
+            0xCF00_0000_1000_0010, // $jmp main_loop
+            0x0000_0000_0000_0000,
+            0xFFFF_0000_0000_0000,
+        ]));
+        let nodes = parse("$funcdef main { $jmp nowhere }").unwrap();
+        assert_eq!(CodeGenerator::new().generate(&nodes), Err(CodegenError::UndefinedLabel(String::from("nowhere"))));
+    }
+
+    #[test]
+    fn conditional_moves_encode_condition_and_registers() {
+        let nodes = parse("$funcdef main { $cmov sle r1, r2, r3, r4 }").unwrap();
+        assert_eq!(CodeGenerator::new().generate(&nodes), Ok(vec![0xCD14_4120_0304_0000]));
+        let nodes = parse("$funcdef main { $cmov gt r16, r2, r3, r4 }").unwrap();
+        assert_eq!(CodeGenerator::new().generate(&nodes), Err(CodegenError::InvalidOperands(String::from("$cmov"))));
+    }
+
+    #[test]
+    fn immediate_comparisons_branch_to_labels() {
+        let nodes = parse("$funcdef main { top: $jmci gt r1, 10, top }").unwrap();
+        assert_eq!(CodeGenerator::new().generate(&nodes), Ok(vec![0xCF0C_A10A_1000_0000]));
+        let nodes = parse("$funcdef main { top: $jmci gt r1, 256, top }").unwrap();
+        assert_eq!(CodeGenerator::new().generate(&nodes), Err(CodegenError::ImmediateTooLarge(256)));
+    }
+
+    #[test]
+    fn bare_instructions_check_their_operands() {
+        let nodes = parse("$funcdef main { $add r1, r2, r3 $set r4, 0x2A $halt }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Ok(vec![0xCD02_0102_0300_0000, 0xCD01_0400_0000_002A, 0xFFFF_0000_0000_0000]));
+        let nodes = parse("$funcdef main { $adc r1, r2, r3 }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Ok(vec![0xCD18_0102_0300_0000]));
+        let nodes = parse("$funcdef main { $neg r1, r2 $abs r2, r3 }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Ok(vec![0xCD19_0102_0000_0000, 0xCD1A_0203_0000_0000]));
+        let nodes = parse("$funcdef main { $popcnt r1, r2 $clz r1, r3 $ctz r1, r4 $bswap r1, r5 }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Ok(vec![
+            0xCE07_0102_0000_0000, 0xCE08_0103_0000_0000, 0xCE09_0104_0000_0000, 0xCE0A_0105_0000_0000
+        ]));
+        let nodes = parse("$funcdef main { $addm r1, 64, r2, 0x10 $subm r3, 8, r13, 0 }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Ok(vec![0xCD16_0140_0200_0010, 0xCD17_0308_0D00_0000]));
+        let nodes = parse("$funcdef main { $addm r1, 12, r2, 0 }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Err(CodegenError::InvalidOperands(String::from("$addm"))));
+        let nodes = parse("$funcdef main { $add r1, r2 }").unwrap();
+        assert_eq!(nodes[0].accept(&mut CodeGenerator::new()), Err(CodegenError::InvalidOperands(String::from("$add"))));
+    }
+
+    #[test]
+    fn recovering_reports_every_syntax_error() {
+        let tokens = Lexer::new(String::from("$funcdef main {\n\t$jmc r1, r2, done\n\t$halt\n\t$vardef = 1\n\tdone: $ret\n}")).tokenize().unwrap();
+        assert_eq!(Parser::new(tokens).parse_recovering().unwrap_err(), vec![
+            ParseError::UnexpectedToken { found: String::from("r1"), expected: "condition", pos: 22, end: 24 },
+            ParseError::UnexpectedToken { found: String::from("="), expected: "variable name", pos: 51, end: 52 }
+        ]);
+        let tokens = Lexer::new(String::from("$funcdef main { $halt }")).tokenize().unwrap();
+        assert_eq!(Parser::new(tokens).parse_recovering().map(|nodes| nodes.len()), Ok(1));
+    }
+
+    #[test]
+    fn reports_unexpected_tokens() {
+        assert_eq!(
+            parse("$funcdef { }").unwrap_err(),
+            ParseError::UnexpectedToken { found: String::from("{"), expected: "function name", pos: 9, end: 10 }
+        );
+        assert_eq!(
+            parse("$funcdef main { $halt").unwrap_err(),
+            ParseError::UnexpectedToken { found: String::from("\0"), expected: "instruction", pos: 21, end: 21 }
+        );
+    }
+}
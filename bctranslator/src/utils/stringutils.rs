@@ -1,4 +1,4 @@
-
+use std::fmt::{Display, Formatter};
 
 pub struct StringBuilder {
     buffer: Vec<char>
@@ -22,4 +22,40 @@ impl StringBuilder {
     pub fn clear(&mut self) {
         self.buffer.clear()
     }
-}
\ No newline at end of file
+    /// Returns the number of chars in the buffer.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+    /// Checks whether nothing was pushed since the last [StringBuilder::clear].
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+    /// Returns the most recently pushed char without removing it.
+    pub fn last(&self) -> Option<char> {
+        self.buffer.last().copied()
+    }
+}
+/// Renders the buffer, which also provides a `to_string` that does not need `&mut`.
+impl Display for StringBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.buffer.iter().try_for_each(|c| write!(f, "{}", c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_do_not_consume_the_buffer() {
+        let mut builder = StringBuilder::new();
+        assert!(builder.is_empty());
+        assert_eq!((builder.len(), builder.last(), builder.to_string()), (0, None, String::new()));
+        builder.push_str("ab");
+        builder.push('ß');
+        assert!(!builder.is_empty());
+        assert_eq!((builder.len(), builder.last()), (3, Some('ß')));
+        assert_eq!(builder.to_string(), "abß");
+        assert_eq!(builder.pack(), "abß");
+    }
+}
@@ -0,0 +1,63 @@
+//! # AetherVM Console Module
+//!
+//! This module provides [ConsoleDevice], a memory-mapped output device that gives bytecode programs a way to
+//! produce text. Every write to the device emits the low byte of the written value to a [Write] sink, which is
+//! stdout by default but can be any writer, e.g. a `Vec<u8>` to capture output in tests.
+//!
+use std::io::{self, Stdout, Write};
+use std::ops::Range;
+
+use crate::hardware::exceptions::Exception;
+use crate::hardware::memory::AVMDevice;
+
+/// The address at which the console is conventionally mapped.
+pub const CONSOLE_ADDRESS: u64 = 0x80000000;
+/// The address range claimed by the console when mapped at [CONSOLE_ADDRESS].
+pub const CONSOLE_RANGE: Range<u64> = CONSOLE_ADDRESS..CONSOLE_ADDRESS + 8;
+
+/// A write-only character device.
+///
+/// Writes of any size output the low byte of the value; reads always return 0.
+pub struct ConsoleDevice<W: Write> {
+    sink: W
+}
+impl ConsoleDevice<Stdout> {
+    /// Creates a console that prints to stdout.
+    pub fn stdout() -> ConsoleDevice<Stdout> {
+        Self::new(io::stdout())
+    }
+}
+impl<W: Write> ConsoleDevice<W> {
+    /// Creates a console that writes to the given sink.
+    pub fn new(sink: W) -> ConsoleDevice<W> {
+        Self { sink }
+    }
+    /// Returns the sink the console writes to.
+    pub fn sink(&self) -> &W {
+        &self.sink
+    }
+}
+impl<W: Write> AVMDevice for ConsoleDevice<W> {
+    fn write(&mut self, addr: u64, data: u64, _size: usize) -> Result<u64, Exception> {
+        // Output is best effort: a closed stdout must not fault the program.
+        let _ = self.sink.write_all(&[data as u8]).and_then(|_| self.sink.flush());
+        Ok(addr)
+    }
+    fn read(&mut self, _addr: u64, _size: usize) -> Result<u64, Exception> {
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn console_writes_low_bytes_to_sink() {
+        let mut console = ConsoleDevice::new(Vec::new());
+        console.write(0, 'H' as u64, 8).unwrap();
+        console.write(0, 0x100 | 'i' as u64, 64).unwrap();
+        assert_eq!(console.sink(), b"Hi");
+        assert_eq!(console.read(0, 8), Ok(0));
+    }
+}
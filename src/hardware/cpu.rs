@@ -5,10 +5,52 @@
 //! and memory interactions. The CPU operates on a set of registers and utilizes a memory bus to interact with external memory
 //! and devices. This documentation outlines the key structures, methods, and functionalities provided by the [AVMCpu] implementation.
 
-use std::collections::VecDeque;
-use std::process::exit;
+use std::collections::HashMap;
+use std::ops::Range;
+use crate::hardware::encode::{decode, Instruction, Operands};
 use crate::hardware::exceptions::Exception;
-use crate::hardware::memory::{AVMBus, AVMDevice, MEMORY_SIZE, MEMORY_START_ADDRESS};
+use crate::hardware::memory::{AVMBus, AVMDevice, AVMMemory, MEMORY_SIZE, MEMORY_START_ADDRESS};
+use crate::hardware::opcode::Opcode;
+use crate::hardware::snapshot::CpuSnapshot;
+
+/// The width of a single encoded instruction in bytes.
+pub const INSTRUCTION_SIZE: u64 = 8;
+/// The size of the stack in bytes. The stack grows down from the base in register 12.
+pub const STACK_SIZE: u64 = 0x100000;
+
+/// Status flag (bit 0) set when the result of `$cmp` is zero, i.e. the operands are equal.
+pub const FLAG_ZERO: u64 = 1 << 0;
+/// Status flag (bit 1) set when `$cmp` borrows, i.e. the first operand is below the second as unsigned values,
+/// or when `$addc`/`$mulc`/`$adc` overflow as unsigned operations. `$adc` also adds it as carry-in.
+pub const FLAG_CARRY: u64 = 1 << 1;
+/// Status flag (bit 2) set when the result of `$cmp` has its most significant bit set.
+pub const FLAG_SIGN: u64 = 1 << 2;
+/// Status flag (bit 3) set when `$cmp` overflows as a signed subtraction, or `$addc`/`$mulc`/`$adc` as signed
+/// operations.
+pub const FLAG_OVERFLOW: u64 = 1 << 3;
+
+/// A host service invoked by `$syscall`.
+///
+/// By convention handlers read their arguments from r1..r5 and leave their result in r0.
+pub type SyscallHandler = Box<dyn FnMut(&mut AVMCpu)>;
+
+/// The result of a fuel-bounded [AVMCpu::run_for] call.
+#[derive(Debug, PartialEq)]
+pub enum RunOutcome {
+    /// The program stopped via `$halt` or `$exit` with the given exit code.
+    Halted(i32),
+    /// The fuel ran out before the program stopped. Execution can be resumed from the current state.
+    OutOfFuel
+}
+
+/// The result of executing a single instruction with [AVMCpu::step].
+#[derive(Debug, PartialEq)]
+pub enum StepOutcome {
+    /// The instruction executed and the program can continue.
+    Running,
+    /// The program stopped via `$halt` or `$exit` with the given exit code.
+    Halted(i32)
+}
 
 /// Represents the virtual CPU for AetherVM.
 ///
@@ -16,10 +58,28 @@ use crate::hardware::memory::{AVMBus, AVMDevice, MEMORY_SIZE, MEMORY_START_ADDRE
 /// - Registers: A fixed array of 32 64-bit general-purpose registers.
 /// - Instruction Pointer (IP): A 64-bit value indicating the current execution address.
 /// - Memory Bus: Facilitates memory access and device communication.
+/// - Flags: A status register written by `$cmp` and read by the flag-based branches
+///   (see [FLAG_ZERO], [FLAG_CARRY], [FLAG_SIGN] and [FLAG_OVERFLOW]).
+/// - Fuel: The remaining instruction budget while running under [AVMCpu::run_for].
+/// - Instruction budget: An optional cap on the instructions a single [AVMCpu::run] may execute.
+/// - Trace hook: An optional callback invoked with the IP and word of every instruction before it executes.
+/// - Syscalls: Host services registered with [AVMCpu::register_syscall] and invoked by `$syscall`.
+/// - Code range: An optional address range instructions may be fetched from, set by the program loader.
 pub struct AVMCpu {
     bus: AVMBus,
     registers: [u64; 32],
     ip: u64,
+    flags: u64,
+    fuel: Option<u64>,
+    max_instructions: Option<u64>,
+    trace: Option<Box<dyn FnMut(u64, u64)>>,
+    syscalls: HashMap<u64, SyscallHandler>,
+    code: Option<Range<u64>>,
+}
+impl Default for AVMCpu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl AVMCpu {
     /// Creates a new instance of the AetherVM CPU.
@@ -28,187 +88,1573 @@ impl AVMCpu {
     /// Registers 12 and 13 are stack base pointer and stack pointer.
     ///
     /// # Returns
-    /// A new [AVMCpu] instance.
+    /// A new [AVMCpu] instance with [MEMORY_SIZE] bytes of memory.
     pub fn new() -> AVMCpu {
+        Self::with_memory_size(MEMORY_SIZE)
+    }
+    /// Creates a new CPU like [AVMCpu::new], with `size` bytes of memory instead of [MEMORY_SIZE].
+    ///
+    /// The stack starts at the end of the smaller memory.
+    pub fn with_memory_size(size: u64) -> AVMCpu {
+        Self::with_memory(AVMMemory::with_size(size))
+    }
+    /// Creates a new CPU around already constructed memory, e.g. [AVMMemory::sparse] to avoid allocating it up front.
+    pub fn with_memory(memory: AVMMemory) -> AVMCpu {
         let mut registers = [0; 32];
-        registers[12] = MEMORY_START_ADDRESS + MEMORY_SIZE;
-        registers[13] = MEMORY_START_ADDRESS + MEMORY_SIZE;
+        registers[12] = MEMORY_START_ADDRESS + memory.size();
+        registers[13] = MEMORY_START_ADDRESS + memory.size();
         Self {
-            bus: AVMBus::new(),
+            bus: AVMBus::with_memory(memory),
             registers,
-            ip: MEMORY_START_ADDRESS
+            ip: MEMORY_START_ADDRESS,
+            flags: 0,
+            fuel: None,
+            max_instructions: None,
+            trace: None,
+            syscalls: HashMap::new(),
+            code: None
         }
     }
+    /// Returns the CPU to the state [AVMCpu::with_memory] left it in, so it can run another program.
+    ///
+    /// Registers, flags and memory are zeroed, r12 and r13 point at the top of the stack again and the IP is
+    /// [MEMORY_START_ADDRESS]. Read-only ranges and the code range of the previous program are removed.
+    /// Mapped devices, registered syscalls, the trace hook and the instruction budget are kept.
+    pub fn reset(&mut self) {
+        self.bus.memory_mut().clear();
+        self.bus.clear_protection();
+        self.registers = [0; 32];
+        self.registers[12] = self.memory_end();
+        self.registers[13] = self.memory_end();
+        self.ip = MEMORY_START_ADDRESS;
+        self.flags = 0;
+        self.fuel = None;
+        self.code = None;
+    }
+    /// Creates a new CPU with the memory size of a serialized snapshot and restores it from that snapshot.
+    ///
+    /// This is the counterpart of pausing a program with [AVMCpu::run_for] and serializing
+    /// [AVMCpu::snapshot] with [CpuSnapshot::to_bytes], allowing it to resume in another process.
+    ///
+    /// # Returns
+    /// - `Ok(AVMCpu)` ready to continue execution where the snapshot was taken.
+    /// - `Err(Exception)` if the snapshot is malformed.
+    pub fn resume(bytes: &[u8]) -> Result<AVMCpu, Exception> {
+        let snapshot = CpuSnapshot::from_bytes(bytes)?;
+        let mut cpu = AVMCpu::with_memory_size(snapshot.memory_size);
+        cpu.restore(&snapshot)?;
+        Ok(cpu)
+    }
+    /// Captures the registers, instruction pointer and written memory pages.
+    ///
+    /// # Returns
+    /// A [CpuSnapshot] of the current state.
+    pub fn snapshot(&self) -> CpuSnapshot {
+        CpuSnapshot {
+            registers: self.registers,
+            ip: self.ip,
+            flags: self.flags,
+            memory_size: self.bus.memory().size(),
+            pages: self.bus.memory().written_pages()
+        }
+    }
+    /// Restores the registers, instruction pointer and memory from a snapshot.
+    ///
+    /// Memory is cleared before the captured pages are written back. Mapped devices are left untouched.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the state was restored.
+    /// - `Err(Exception)` if a captured page does not fit into memory.
+    pub fn restore(&mut self, snapshot: &CpuSnapshot) -> Result<(), Exception> {
+        let memory = self.bus.memory_mut();
+        memory.clear();
+        memory.write_pages(&snapshot.pages)?;
+        self.registers = snapshot.registers;
+        self.ip = snapshot.ip;
+        self.flags = snapshot.flags;
+        Ok(())
+    }
+    /// Returns the value of a single register.
+    ///
+    /// # Returns
+    /// - `Ok(u64)` with the register value.
+    /// - `Err(Exception::IllegalRegisterIndex)` if `index` is not in `0..32`.
+    pub fn register(&self, index: usize) -> Result<u64, Exception> {
+        self.registers.get(index).copied().ok_or(Exception::IllegalRegisterIndex(index))
+    }
+    /// Sets the value of a single register, e.g. from a syscall handler.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the register was written.
+    /// - `Err(Exception::IllegalRegisterIndex)` if `index` is not in `0..32`.
+    pub fn set_register(&mut self, index: usize, value: u64) -> Result<(), Exception> {
+        let register = self.registers.get_mut(index).ok_or(Exception::IllegalRegisterIndex(index))?;
+        *register = value;
+        Ok(())
+    }
+    /// Returns all 32 registers.
+    pub fn registers(&self) -> &[u64; 32] {
+        &self.registers
+    }
+    /// Returns the current instruction pointer.
+    pub fn ip(&self) -> u64 {
+        self.ip
+    }
+    /// Returns the status flags.
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+    /// Returns the memory bus, e.g. to map devices before running a program.
+    pub fn bus_mut(&mut self) -> &mut AVMBus {
+        &mut self.bus
+    }
     /// Loads the next instruction from memory.
     ///
     /// The instruction is read as a 64-bit value from the memory address pointed to by the instruction pointer (IP).
     ///
     /// # Returns
     /// - `Ok(u64)` if the instruction is successfully loaded.
-    /// - `Err(Exception)` if there is an error during memory access.
+    /// - `Err(Exception::InstructionAccessFaultOnAddress)` if the IP lies outside the code range or memory.
     pub fn load_instr(&mut self) -> Result<u64, Exception> {
+        if self.code.as_ref().is_some_and(|code| !code.contains(&self.ip)) {
+            return Err(Exception::InstructionAccessFaultOnAddress(self.ip))
+        }
         match self.bus.read(self.ip, 64) {
             Ok(instr) => Ok(instr),
             Err(_) => Err(Exception::InstructionAccessFaultOnAddress(self.ip))
         }
     }
+    /// Restricts instruction fetches to `code`, so that running off the end of a program faults instead of
+    /// executing zeroed memory as `$nop`s.
+    ///
+    /// `None`, the default, allows fetching from anywhere in memory.
+    pub fn set_code_range(&mut self, code: Option<Range<u64>>) {
+        self.code = code;
+    }
+    /// Limits how many instructions [AVMCpu::run] may execute before giving up.
+    ///
+    /// `None`, the default, lets programs run unbounded.
+    pub fn set_instruction_budget(&mut self, max_instructions: Option<u64>) {
+        self.max_instructions = max_instructions;
+    }
+    /// Installs a callback that [AVMCpu::step], and therefore every run loop, invokes with the
+    /// instruction pointer and the encoded instruction before executing it.
+    ///
+    /// `None` removes a previously installed hook.
+    pub fn set_trace_hook(&mut self, hook: Option<Box<dyn FnMut(u64, u64)>>) {
+        self.trace = hook;
+    }
+    /// Registers the handler `$syscall` invokes for `number`, replacing any previous one.
+    pub fn register_syscall(&mut self, number: u64, handler: SyscallHandler) {
+        self.syscalls.insert(number, handler);
+    }
+    /// Runs the loaded program until it stops.
+    ///
+    /// Repeatedly loads and executes instructions starting at the current instruction pointer (IP).
+    ///
+    /// # Returns
+    /// - `Ok(i32)` with the exit code once the program stops via `$halt` or `$exit`.
+    /// - `Err(Exception::InstructionBudgetExceeded)` if the program runs past the instruction budget.
+    /// - `Err(Exception)` if an instruction faults.
+    pub fn run(&mut self) -> Result<i32, Exception> {
+        let mut executed = 0;
+        loop {
+            if let Some(max) = self.max_instructions {
+                if executed == max {
+                    return Err(Exception::InstructionBudgetExceeded(max))
+                }
+            }
+            if let StepOutcome::Halted(code) = self.step()? {
+                return Ok(code)
+            }
+            executed += 1;
+        }
+    }
+    /// Loads and executes exactly one instruction.
+    ///
+    /// # Returns
+    /// - `Ok(StepOutcome::Running)` if the program can continue.
+    /// - `Ok(StepOutcome::Halted)` with the exit code if the instruction stopped the program.
+    /// - `Err(Exception::Fault)` with the instruction's address and the raised exception if the instruction faults.
+    /// - `Err(Exception::InstructionAccessFaultOnAddress)` if no instruction can be loaded.
+    pub fn step(&mut self) -> Result<StepOutcome, Exception> {
+        let ip = self.ip;
+        let instr = self.load_instr()?;
+        if let Some(trace) = self.trace.as_mut() {
+            trace(ip, instr);
+        }
+        self.fuel = self.fuel.map(|fuel| fuel - 1);
+        match self.execute_instr(instr) {
+            Ok(()) => Ok(StepOutcome::Running),
+            Err(Exception::Halt(code)) => Ok(StepOutcome::Halted(code)),
+            Err(e) => Err(Exception::Fault { ip, cause: Box::new(e) })
+        }
+    }
+    /// Runs the loaded program for at most `fuel` instructions.
+    ///
+    /// When the fuel runs out the CPU stops at an instruction boundary, so its [AVMCpu::snapshot]
+    /// is sufficient to resume the program identically, even after serialization.
+    /// Each instruction consumes its fuel before executing, so `$fuel` reports the budget left after itself.
+    ///
+    /// # Returns
+    /// - `Ok(RunOutcome::Halted)` with the exit code if the program stopped.
+    /// - `Ok(RunOutcome::OutOfFuel)` if the fuel ran out first.
+    /// - `Err(Exception)` if an instruction faults.
+    pub fn run_for(&mut self, fuel: u64) -> Result<RunOutcome, Exception> {
+        self.fuel = Some(fuel);
+        let outcome = loop {
+            if self.fuel == Some(0) {
+                break Ok(RunOutcome::OutOfFuel)
+            }
+            match self.step() {
+                Ok(StepOutcome::Running) => {}
+                Ok(StepOutcome::Halted(code)) => break Ok(RunOutcome::Halted(code)),
+                Err(e) => break Err(e)
+            }
+        };
+        self.fuel = None;
+        outcome
+    }
     /// Executes a given instruction.
     ///
     /// Decodes the instruction, performs the corresponding operation, and updates the instruction pointer.
     /// Supports various operations including register manipulation, arithmetic, jumps, and memory access.
+    /// Shift amounts are taken modulo 64, so shifting by 64 or more is deterministic.
+    /// `$popcnt`, `$clz` and `$ctz` count the set bits, leading zeros and trailing zeros; zero has 64 of both.
+    /// `$bswap` reverses the byte order of a register, independently of the memory's endianness.
+    /// Floating-point operations reinterpret register bits as `f64` and follow IEEE 754: NaN operands
+    /// propagate, overflow and division by zero produce infinities, and none of them raise an exception.
+    /// `$ftoi` truncates toward zero and saturates like an `as` cast: NaN becomes 0 and out-of-range
+    /// values clamp to `i64::MIN`/`i64::MAX`.
+    ///
+    /// Integer arithmetic treats registers as unsigned `u64` and wraps, except for `$sdiv` and `$srem`,
+    /// which interpret them as `i64` and truncate toward zero (`i64::MIN $sdiv -1` wraps to `i64::MIN`).
+    /// Every integer division raises [Exception::DivisionByZero] for a zero divisor.
+    /// `$neg` negates in two's complement and `$abs` takes the absolute value as `i64`; both wrap, so `i64::MIN`
+    /// stays `i64::MIN`.
+    /// `$jmc` compares unsigned values with conditions `0xA`..`0xF`, signed values with `0x1`..`0x4` and
+    /// floating-point values with `0x5`..`0x9`.
+    /// `$addc` and `$mulc` wrap like `$add` and `$mul` but also replace the status flags like `$cmp` does:
+    /// carry is set when the unsigned result overflowed, overflow when the signed result did.
+    /// `$adc` additionally adds the incoming carry flag, so chaining it adds values spanning several registers.
+    ///
+    /// # Encoding
+    /// The opcode always occupies bits 48..64. Operand layouts that are not plain register fields:
+    /// - `$set`: destination register in bits 40..48, unsigned 40-bit immediate in bits 0..40.
+    /// - `$lea`: destination register in bits 40..48, 40-bit address in bits 0..40. Unlike `$set`, the address
+    ///   must lie within memory, otherwise [Exception::AddressNotInMemoryBounds] is returned.
+    /// - `$jmp`, `$call`, `$jz`, `$jnz`, `$js`, `$jns`, `$jc`, `$jnc`, `$jo`, `$jno`: target address in bits 0..48.
+    /// - `$syscall`: syscall number in bits 0..48.
+    /// - `$jmc`: condition in bits 44..48, first register in bits 40..44, second register in bits 36..40
+    ///   and target address in bits 0..36.
+    /// - `$jmci`: condition in bits 44..48, register in bits 40..44, unsigned 8-bit immediate in bits 32..40
+    ///   and target address in bits 0..32. The register is compared against the immediate like `$jmc` does.
+    /// - `$cmov`: condition and compared registers as in `$jmc`, source register in bits 24..32 and destination
+    ///   register in bits 16..24. The destination is left unchanged if the condition does not hold.
+    /// - `$cas`: address, expected and new value registers in the usual fields, result register in bits 16..24.
+    ///
+    /// `$callr` calls the address held in its register, like `$call` does with its immediate address.
+    /// Jump and call targets must lie within memory, otherwise [Exception::AddressNotInMemoryBounds] is returned.
+    ///
+    /// `$cas` compares the 64-bit word at the address with the expected value and only writes the new value if
+    /// they are equal, as one instruction. The result register receives 1 if the word was swapped and 0 otherwise.
+    ///
+    /// `$addm` and `$subm` read the value of the given size at `[baseReg + disp]`, add or subtract the register with
+    /// wrap-around at that size, and write the result back, all in one instruction. They use the layout of the
+    /// base-relative `$move`.
+    ///
+    /// `$memcpy` copies bytes as if through an intermediate buffer, so overlapping ranges are handled.
+    /// Both ranges must lie entirely within memory before a single byte is copied.
     ///
     /// # Parameters
     /// - `instr`: A 64-bit value representing the instruction to execute.
     ///
     /// # Returns
     /// - `Ok(())` if the instruction executes successfully.
+    /// - `Err(Exception::Halt)` if the program stopped via `$halt` or `$exit`.
     /// - `Err(Exception)` if an error occurs (e.g., unexpected opcode or invalid arguments).
     pub fn execute_instr(&mut self, instr: u64) -> Result<(), Exception> {
-        let operation = instr >> 48;
-        let cda1 = ((instr >> 32) & 0x0000FF00) >> 8;
-        let cda2 = (instr >> 32) & 0x000000FF;
-        let cda3 = (instr >> 24) & 0x00000000FF;
-        let eda1 = (instr >> 32) & 0x0000FFFF;
-        let eda2 = (instr >> 16) & 0x00000000FFFF;
-        let eda3 =  instr & 0x000000000000FFFF;
-        let ota = instr & 0x00000000FFFFFFFF;
-        match operation {
-            0xCD00 => { //[regFrom] $move [regTo]
-                let reg_from = cda1 as usize;
-                let reg_to = cda2 as usize;
+        self.execute(decode(instr)?)
+    }
+    /// Executes an already decoded instruction, see [AVMCpu::execute_instr] for the semantics of each operation.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the instruction executes successfully.
+    /// - `Err(Exception::Halt)` if the program stopped via `$halt` or `$exit`.
+    /// - `Err(Exception::UnexpectedOpcode)` if the operands do not have the layout of the operation.
+    /// - `Err(Exception::IllegalRegisterIndex)` if a register field is not in `0..32`.
+    /// - `Err(Exception)` if another error occurs.
+    pub fn execute(&mut self, instruction: Instruction) -> Result<(), Exception> {
+        if let Some(reg) = instruction.registers().into_iter().find(|&reg| reg >= self.registers.len()) {
+            return Err(Exception::IllegalRegisterIndex(reg))
+        }
+        let Instruction { opcode, operands } = instruction;
+        match (opcode, operands) {
+            (Opcode::Nop, Operands::None) => { //$nop
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Move, Operands::Registers { a: reg_from, b: reg_to, .. }) => { //[regFrom] $move [regTo]
                 self.registers[reg_to] = self.registers[reg_from];
-                self.ip += 1;
+                self.ip += INSTRUCTION_SIZE;
                 Ok(())
             }
-            0xCD01 => { //[reg] $set [value]
-                let reg = cda1 as usize;
-                let value = eda2 | eda3 | (eda1 & 0xFF);
+            (Opcode::Set, Operands::Immediate { reg, value }) => { //[reg] $set [value]
                 self.registers[reg] = value;
-                self.ip += 1;
+                self.ip += INSTRUCTION_SIZE;
                 Ok(())
             }
-            0xCF00 => { //$jmp [address]
-                let address = eda1 | eda2 | eda3;
-                self.ip = address;
+            (Opcode::Lea, Operands::Immediate { reg, value: address }) => { //[reg] $lea [address]
+                self.check_range(address, 1)?;
+                self.registers[reg] = address;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Push, Operands::Registers { a: reg, .. }) => { //$push [reg]
+                self.push(self.registers[reg])?;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Pop, Operands::Registers { a: reg, .. }) => { //$pop [reg]
+                self.registers[reg] = self.pop()?;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Jmp, Operands::Address(address)) => { //$jmp [address]
+                self.ip = self.jump_target(address)?;
+                Ok(())
+            }
+            (Opcode::Jmc, Operands::Branch { cond, lhs, rhs, address }) => { //[reg1] $jmc [cond] [reg2]
+                let address = self.jump_target(address)?;
+                let taken = Self::condition_holds(cond, self.registers[lhs], self.registers[rhs])?;
+                self.branch_if(taken, address);
                 Ok(())
             }
-            0xCF01 => { //[reg1] $jmc [cond] [reg2]
-                let address = (eda1 | eda3 | eda2) & 0x000FFFFFFFFF;
-                let condition = cda1 >> 4;
-                let reg1 = (cda1 & 0x0F) as usize;
-                let reg2 = (cda2 >> 4) as usize;
-
-                match condition {
-                    0xA => {
-                        if self.registers[reg1] > self.registers[reg2] {
-                            self.ip = address;
-                            return Ok(())
-                        }
-                        Ok(())
-                    }
-                    0xB => {
-                        if self.registers[reg1] < self.registers[reg2] {
-                            self.ip = address;
-                            return Ok(())
-                        }
-                        Ok(())
-                    }
-                    0xC => {
-                        if self.registers[reg1] == self.registers[reg2] {
-                            self.ip = address;
-                            return Ok(())
-                        }
-                        Ok(())
-                    }
-                    0xD => {
-                        if self.registers[reg1] >= self.registers[reg2] {
-                            self.ip = address;
-                            return Ok(())
-                        }
-                        Ok(())
-                    }
-                    0xE => {
-                        if self.registers[reg1] <= self.registers[reg2] {
-                            self.ip = address;
-                            return Ok(())
-                        }
-                        Ok(())
-                    }
-                    _ => {
-                        Err(Exception::UnexpectedCondition(condition))
-                    }
+            (Opcode::Cmov, Operands::ConditionalMove { cond, lhs, rhs, src, dst }) => { //[reg1] $cmov [cond] [reg2] [src] -> [dst]
+                if Self::condition_holds(cond, self.registers[lhs], self.registers[rhs])? {
+                    self.registers[dst] = self.registers[src];
                 }
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Cas, Operands::CompareAndSwap { address, expected, new, result }) => { //[addr] $cas [expected] [new] -> [result]
+                let address = self.registers[address];
+                let swapped = self.bus.read(address, 64)? == self.registers[expected];
+                if swapped {
+                    self.bus.write(address, self.registers[new], 64)?;
+                }
+                self.registers[result] = swapped as u64;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Jmci, Operands::BranchImmediate { cond, reg, imm, address }) => { //[reg] $jmci [cond] [imm]
+                let address = self.jump_target(address)?;
+                let taken = Self::condition_holds(cond, self.registers[reg], imm)?;
+                self.branch_if(taken, address);
+                Ok(())
+            }
+            (Opcode::Jz, Operands::Address(address)) => { //$jz [address]
+                let address = self.jump_target(address)?;
+                self.branch_if(self.flags & FLAG_ZERO != 0, address);
+                Ok(())
+            }
+            (Opcode::Jnz, Operands::Address(address)) => { //$jnz [address]
+                let address = self.jump_target(address)?;
+                self.branch_if(self.flags & FLAG_ZERO == 0, address);
+                Ok(())
+            }
+            (Opcode::Js, Operands::Address(address)) => { //$js [address]
+                let address = self.jump_target(address)?;
+                self.branch_if(self.flags & FLAG_SIGN != 0, address);
+                Ok(())
+            }
+            (Opcode::Jns, Operands::Address(address)) => { //$jns [address]
+                let address = self.jump_target(address)?;
+                self.branch_if(self.flags & FLAG_SIGN == 0, address);
+                Ok(())
+            }
+            (Opcode::Jc, Operands::Address(address)) => { //$jc [address]
+                let address = self.jump_target(address)?;
+                self.branch_if(self.flags & FLAG_CARRY != 0, address);
+                Ok(())
+            }
+            (Opcode::Jnc, Operands::Address(address)) => { //$jnc [address]
+                let address = self.jump_target(address)?;
+                self.branch_if(self.flags & FLAG_CARRY == 0, address);
+                Ok(())
+            }
+            (Opcode::Jo, Operands::Address(address)) => { //$jo [address]
+                let address = self.jump_target(address)?;
+                self.branch_if(self.flags & FLAG_OVERFLOW != 0, address);
+                Ok(())
+            }
+            (Opcode::Jno, Operands::Address(address)) => { //$jno [address]
+                let address = self.jump_target(address)?;
+                self.branch_if(self.flags & FLAG_OVERFLOW == 0, address);
+                Ok(())
             }
-            0xCD02 => { //[reg1] $add [reg2] [resReg]
-                let reg1 = cda1 as usize;
-                let reg2 = cda2 as usize;
-                let res_reg = cda3 as usize;
+            (Opcode::Call, Operands::Address(address)) => { //$call [address]
+                let address = self.jump_target(address)?;
+                self.push(self.ip + INSTRUCTION_SIZE)?;
+                self.ip = address;
+                Ok(())
+            }
+            (Opcode::Callr, Operands::Registers { a: reg, .. }) => { //$callr [reg]
+                let address = self.jump_target(self.registers[reg])?;
+                self.push(self.ip + INSTRUCTION_SIZE)?;
+                self.ip = address;
+                Ok(())
+            }
+            (Opcode::Ret, Operands::None) => { //$ret
+                self.ip = self.pop()?;
+                Ok(())
+            }
+            (Opcode::Add, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $add [reg2] [resReg]
                 self.registers[res_reg] = self.registers[reg1].wrapping_add(self.registers[reg2]);
-                self.ip += 1;
+                self.ip += INSTRUCTION_SIZE;
                 Ok(())
-
             }
-            0xCD03 => { //[reg1] $sub [reg2] [resReg]
-                let reg1 = cda1 as usize;
-                let reg2 = cda2 as usize;
-                let res_reg = cda3 as usize;
+            (Opcode::Sub, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $sub [reg2] [resReg]
                 self.registers[res_reg] = self.registers[reg1].wrapping_sub(self.registers[reg2]);
-                self.ip += 1;
+                self.ip += INSTRUCTION_SIZE;
                 Ok(())
-
             }
-            0xCD04 => { //[reg1] $mul [reg2] [resReg]
-                let reg1 = cda1 as usize;
-                let reg2 = cda2 as usize;
-                let res_reg = cda3 as usize;
+            (Opcode::Mul, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $mul [reg2] [resReg]
                 self.registers[res_reg] = self.registers[reg1].wrapping_mul(self.registers[reg2]);
-                self.ip += 1;
+                self.ip += INSTRUCTION_SIZE;
                 Ok(())
-
             }
-            0xCD05 => { //[reg1] $div [reg2] [resReg]
-                let reg1 = cda1 as usize;
-                let reg2 = cda2 as usize;
-                let res_reg = cda3 as usize;
+            (Opcode::Div, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $div [reg2] [resReg]
+                if self.registers[reg2] == 0 {
+                    return Err(Exception::DivisionByZero)
+                }
                 self.registers[res_reg] = self.registers[reg1].wrapping_div(self.registers[reg2]);
-                self.ip += 1;
+                self.ip += INSTRUCTION_SIZE;
                 Ok(())
-
             }
-            0xCD06 => { //[address] $move [reg]
-                let address = eda2 | eda3;
-                let reg = cda1 as usize;
-                let size = cda2 as usize;
-                if let Ok(val) = self.bus.read(address, size) {
-                    self.registers[reg] = val;
-                    self.ip += 1;
-                    Ok(())
-                } else {
-                    Err(Exception::IllegalSizeArgument(size))
+            (Opcode::Sdiv | Opcode::Srem, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $sdiv/$srem [reg2] [resReg]
+                let a = self.registers[reg1] as i64;
+                let b = self.registers[reg2] as i64;
+                if b == 0 {
+                    return Err(Exception::DivisionByZero)
                 }
+                let result = if opcode == Opcode::Sdiv { a.wrapping_div(b) } else { a.wrapping_rem(b) };
+                self.registers[res_reg] = result as u64;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::LoadAbsolute | Opcode::StoreAbsolute, Operands::Absolute { size, .. }) if !matches!(size, 8 | 16 | 32 | 64) => {
+                Err(Exception::IllegalSizeArgument(size))
             }
-            0xCD07 => { //[reg] $move [address]
-                let address = eda2 | eda3;
-                let reg = cda1 as usize;
-                let size = cda2 as usize;
-                if let Ok(addr) = self.bus.write(address, self.registers[reg], size) {
-                    self.ip += 1;
-                    Ok(())
+            (Opcode::LoadAbsolute, Operands::Absolute { reg, size, address }) => { //[address] $move [reg]
+                self.registers[reg] = self.bus.read(address, size)?;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::StoreAbsolute, Operands::Absolute { reg, size, address }) => { //[reg] $move [address]
+                self.bus.write(address, self.registers[reg], size)?;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Load, Operands::Memory { reg, size, base, disp }) => { //[baseReg + disp] $move [reg]
+                let address = self.effective_address(self.registers[base], disp, size)?;
+                self.registers[reg] = self.bus.read(address, size)?;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Store, Operands::Memory { reg, size, base, disp }) => { //[reg] $move [baseReg + disp]
+                let address = self.effective_address(self.registers[base], disp, size)?;
+                self.bus.write(address, self.registers[reg], size)?;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Addm | Opcode::Subm, Operands::Memory { reg, size, base, disp }) => { //[reg] $addm/$subm [baseReg + disp]
+                let address = self.effective_address(self.registers[base], disp, size)?;
+                let value = self.bus.read(address, size)?;
+                let operand = self.registers[reg];
+                let result = if opcode == Opcode::Addm { value.wrapping_add(operand) } else { value.wrapping_sub(operand) };
+                self.bus.write(address, result, size)?;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Cmp, Operands::Registers { a: reg1, b: reg2, .. }) => { //[reg1] $cmp [reg2]
+                let a = self.registers[reg1];
+                let b = self.registers[reg2];
+                let (result, borrow) = a.overflowing_sub(b);
+                let (_, overflow) = (a as i64).overflowing_sub(b as i64);
+                self.set_flags(result, borrow, overflow);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Addc | Opcode::Mulc, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $addc/$mulc [reg2] [resReg]
+                let a = self.registers[reg1];
+                let b = self.registers[reg2];
+                let (result, carry, overflow) = if opcode == Opcode::Addc {
+                    (a.wrapping_add(b), a.checked_add(b).is_none(), (a as i64).checked_add(b as i64).is_none())
                 } else {
-                    Err(Exception::IllegalSizeArgument(size))
+                    (a.wrapping_mul(b), a.checked_mul(b).is_none(), (a as i64).checked_mul(b as i64).is_none())
+                };
+                self.registers[res_reg] = result;
+                self.set_flags(result, carry, overflow);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Adc, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $adc [reg2] [resReg]
+                let a = self.registers[reg1];
+                let b = self.registers[reg2];
+                let carry_in = (self.flags & FLAG_CARRY != 0) as u64;
+                let (sum, carry1) = a.overflowing_add(b);
+                let (result, carry2) = sum.overflowing_add(carry_in);
+                let (signed_sum, overflow1) = (a as i64).overflowing_add(b as i64);
+                let (_, overflow2) = signed_sum.overflowing_add(carry_in as i64);
+                self.registers[res_reg] = result;
+                self.set_flags(result, carry1 || carry2, overflow1 != overflow2);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Neg, Operands::Registers { a: src_reg, b: dest_reg, .. }) => { //[srcReg] $neg [destReg]
+                self.registers[dest_reg] = 0u64.wrapping_sub(self.registers[src_reg]);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Abs, Operands::Registers { a: src_reg, b: dest_reg, .. }) => { //[srcReg] $abs [destReg]
+                self.registers[dest_reg] = (self.registers[src_reg] as i64).wrapping_abs() as u64;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Swap, Operands::Registers { a: reg1, b: reg2, .. }) => { //[reg1] $swap [reg2]
+                self.registers.swap(reg1, reg2);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Inc, Operands::Registers { a: reg, .. }) => { //$inc [reg]
+                self.registers[reg] = self.registers[reg].wrapping_add(1);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Dec, Operands::Registers { a: reg, .. }) => { //$dec [reg]
+                self.registers[reg] = self.registers[reg].wrapping_sub(1);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Memcpy, Operands::Registers { a: dest_reg, b: src_reg, c: len_reg }) => { //[destReg] $memcpy [srcReg] [lenReg]
+                let dest = self.registers[dest_reg];
+                let src = self.registers[src_reg];
+                let len = self.registers[len_reg];
+                self.check_range(src, len)?;
+                self.check_range(dest, len)?;
+                let mut buffer = Vec::with_capacity(len as usize);
+                for offset in 0..len {
+                    buffer.push(self.bus.read(src + offset, 8)? as u8);
                 }
+                for (offset, byte) in (0..len).zip(buffer) {
+                    self.bus.write(dest + offset, byte as u64, 8)?;
+                }
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Fadd, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $fadd [reg2] [resReg]
+                self.registers[res_reg] = (f64::from_bits(self.registers[reg1]) + f64::from_bits(self.registers[reg2])).to_bits();
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
             }
-            0xFFFF => { //$halt
-                exit(0)
+            (Opcode::Fsub, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $fsub [reg2] [resReg]
+                self.registers[res_reg] = (f64::from_bits(self.registers[reg1]) - f64::from_bits(self.registers[reg2])).to_bits();
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
             }
-            0xFFFA => { //$exit [code] / $quit [code]
-                let code = cda1 as i32;
-                exit(code)
+            (Opcode::Fmul, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $fmul [reg2] [resReg]
+                self.registers[res_reg] = (f64::from_bits(self.registers[reg1]) * f64::from_bits(self.registers[reg2])).to_bits();
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
             }
-            _ => {
-                Err(Exception::UnexpectedOpcode(operation))
+            (Opcode::Fdiv, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $fdiv [reg2] [resReg]
+                self.registers[res_reg] = (f64::from_bits(self.registers[reg1]) / f64::from_bits(self.registers[reg2])).to_bits();
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Itof, Operands::Registers { a: reg, b: res_reg, .. }) => { //[reg] $itof [resReg]
+                self.registers[res_reg] = (self.registers[reg] as i64 as f64).to_bits();
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Ftoi, Operands::Registers { a: reg, b: res_reg, .. }) => { //[reg] $ftoi [resReg]
+                self.registers[res_reg] = f64::from_bits(self.registers[reg]) as i64 as u64;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::And, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $and [reg2] [resReg]
+                self.registers[res_reg] = self.registers[reg1] & self.registers[reg2];
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Or, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $or [reg2] [resReg]
+                self.registers[res_reg] = self.registers[reg1] | self.registers[reg2];
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Xor, Operands::Registers { a: reg1, b: reg2, c: res_reg }) => { //[reg1] $xor [reg2] [resReg]
+                self.registers[res_reg] = self.registers[reg1] ^ self.registers[reg2];
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Not, Operands::Registers { a: reg, b: res_reg, .. }) => { //[reg] $not [resReg]
+                self.registers[res_reg] = !self.registers[reg];
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Shl, Operands::Registers { a: reg, b: amount_reg, c: res_reg }) => { //[reg] $shl [amountReg] [resReg]
+                let amount = (self.registers[amount_reg] % 64) as u32;
+                self.registers[res_reg] = self.registers[reg].wrapping_shl(amount);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
             }
+            (Opcode::Shr, Operands::Registers { a: reg, b: amount_reg, c: res_reg }) => { //[reg] $shr [amountReg] [resReg]
+                let amount = (self.registers[amount_reg] % 64) as u32;
+                self.registers[res_reg] = self.registers[reg].wrapping_shr(amount);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Sar, Operands::Registers { a: reg, b: amount_reg, c: res_reg }) => { //[reg] $sar [amountReg] [resReg]
+                let amount = (self.registers[amount_reg] % 64) as u32;
+                self.registers[res_reg] = (self.registers[reg] as i64).wrapping_shr(amount) as u64;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Popcnt | Opcode::Clz | Opcode::Ctz, Operands::Registers { a: src_reg, b: dest_reg, .. }) => { //[srcReg] $popcnt/$clz/$ctz [destReg]
+                let value = self.registers[src_reg];
+                let count = match opcode {
+                    Opcode::Popcnt => value.count_ones(),
+                    Opcode::Clz => value.leading_zeros(),
+                    _ => value.trailing_zeros()
+                };
+                self.registers[dest_reg] = count as u64;
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Bswap, Operands::Registers { a: src_reg, b: dest_reg, .. }) => { //[srcReg] $bswap [destReg]
+                self.registers[dest_reg] = self.registers[src_reg].swap_bytes();
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Halt, Operands::None) => { //$halt
+                Err(Exception::Halt(0))
+            }
+            (Opcode::Exit, Operands::Code(code)) => { //$exit [code] / $quit [code]
+                Err(Exception::Halt(code as i32))
+            }
+            (Opcode::Syscall, Operands::Number(number)) => { //$syscall [number]
+                let mut handler = self.syscalls.remove(&number).ok_or(Exception::UnknownSyscall(number))?;
+                handler(self);
+                self.syscalls.entry(number).or_insert(handler);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (Opcode::Fuel, Operands::Registers { a: res_reg, .. }) => { //$fuel [resReg]
+                self.registers[res_reg] = self.fuel.unwrap_or(u64::MAX);
+                self.ip += INSTRUCTION_SIZE;
+                Ok(())
+            }
+            (opcode, _) => Err(Exception::UnexpectedOpcode(opcode as u64))
+        }
+    }
+    /// Pushes a value onto the stack, moving the stack pointer (register 13) down by 8 bytes.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the value was pushed.
+    /// - `Err(Exception::StackOverflow)` if the stack pointer would cross below `r12 - STACK_SIZE`.
+    fn push(&mut self, value: u64) -> Result<(), Exception> {
+        let sp = self.registers[13];
+        let limit = self.registers[12].saturating_sub(STACK_SIZE);
+        match sp.checked_sub(8) {
+            Some(new_sp) if new_sp >= limit => {
+                self.bus.write(new_sp, value, 64)?;
+                self.registers[13] = new_sp;
+                Ok(())
+            }
+            _ => Err(Exception::StackOverflow(sp))
+        }
+    }
+    /// Pops a value from the stack, moving the stack pointer (register 13) up by 8 bytes.
+    ///
+    /// # Returns
+    /// - `Ok(u64)` with the popped value.
+    /// - `Err(Exception::StackUnderflow)` if the stack pointer would move above the stack base (register 12).
+    fn pop(&mut self) -> Result<u64, Exception> {
+        let sp = self.registers[13];
+        match sp.checked_add(8) {
+            Some(new_sp) if new_sp <= self.registers[12] => {
+                let value = self.bus.read(sp, 64)?;
+                self.registers[13] = new_sp;
+                Ok(value)
+            }
+            _ => Err(Exception::StackUnderflow(sp))
+        }
+    }
+    /// Evaluates a `$jmc` condition code on two register values.
+    ///
+    /// Supported conditions are, comparing as `u64`, `0xA` (>), `0xB` (<), `0xC` (==), `0xD` (>=), `0xE` (<=) and
+    /// `0xF` (!=); comparing as `i64`, `0x1` (>), `0x2` (<), `0x3` (>=) and `0x4` (<=); and comparing as `f64`,
+    /// `0x5` (>), `0x6` (<), `0x7` (==), `0x8` (>=) and `0x9` (<=). Float conditions are false if either value is NaN
+    /// and treat `-0.0` and `0.0` as equal.
+    ///
+    /// # Returns
+    /// - `Ok(bool)` telling whether the condition holds.
+    /// - `Err(Exception::UnexpectedCondition)` for any other condition code.
+    fn condition_holds(condition: u64, a: u64, b: u64) -> Result<bool, Exception> {
+        let (sa, sb) = (a as i64, b as i64);
+        match condition {
+            0x1 => Ok(sa > sb),
+            0x2 => Ok(sa < sb),
+            0x3 => Ok(sa >= sb),
+            0x4 => Ok(sa <= sb),
+            0x5 => Ok(f64::from_bits(a) > f64::from_bits(b)),
+            0x6 => Ok(f64::from_bits(a) < f64::from_bits(b)),
+            0x7 => Ok(f64::from_bits(a) == f64::from_bits(b)),
+            0x8 => Ok(f64::from_bits(a) >= f64::from_bits(b)),
+            0x9 => Ok(f64::from_bits(a) <= f64::from_bits(b)),
+            0xA => Ok(a > b),
+            0xB => Ok(a < b),
+            0xC => Ok(a == b),
+            0xD => Ok(a >= b),
+            0xE => Ok(a <= b),
+            0xF => Ok(a != b),
+            _ => Err(Exception::UnexpectedCondition(condition))
+        }
+    }
+    /// Replaces the status flags with those describing `result`.
+    fn set_flags(&mut self, result: u64, carry: bool, overflow: bool) {
+        self.flags = 0;
+        if result == 0 { self.flags |= FLAG_ZERO }
+        if carry { self.flags |= FLAG_CARRY }
+        if (result as i64) < 0 { self.flags |= FLAG_SIGN }
+        if overflow { self.flags |= FLAG_OVERFLOW }
+    }
+    /// Moves the IP to `address` if the branch is taken, or to the next instruction otherwise.
+    fn branch_if(&mut self, taken: bool, address: u64) {
+        if taken {
+            self.ip = address;
+        } else {
+            self.ip += INSTRUCTION_SIZE;
+        }
+    }
+    /// Returns the first address past the end of memory.
+    fn memory_end(&self) -> u64 {
+        MEMORY_START_ADDRESS + self.bus.memory().size()
+    }
+    /// Validates that a jump or call target lies within memory.
+    ///
+    /// # Returns
+    /// - `Ok(u64)` with the target address.
+    /// - `Err(Exception::AddressNotInMemoryBounds)` if the target is outside memory.
+    fn jump_target(&self, address: u64) -> Result<u64, Exception> {
+        if (MEMORY_START_ADDRESS..self.memory_end()).contains(&address) {
+            Ok(address)
+        } else {
+            Err(Exception::AddressNotInMemoryBounds(address))
+        }
+    }
+    /// Validates that the `len` bytes starting at `address` lie within memory.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the whole range is in memory.
+    /// - `Err(Exception::AddressNotInMemoryBounds)` with the start address otherwise.
+    fn check_range(&self, address: u64, len: u64) -> Result<(), Exception> {
+        match address.checked_add(len) {
+            Some(end) if address >= MEMORY_START_ADDRESS && end <= self.memory_end() => Ok(()),
+            _ => Err(Exception::AddressNotInMemoryBounds(address))
+        }
+    }
+    /// Computes the effective address `base + disp` for a memory access of `size` bits.
+    ///
+    /// Base-relative instructions encode the base register in bits 24..32 and an unsigned
+    /// 24-bit displacement in bits 0..24.
+    ///
+    /// # Returns
+    /// - `Ok(u64)` if the whole access lies within memory.
+    /// - `Err(Exception::AddressNotInMemoryBounds)` if the sum overflows or the access leaves memory.
+    /// - `Err(Exception::IllegalSizeArgument)` if `size` is not 8, 16, 32 or 64.
+    fn effective_address(&self, base: u64, disp: u64, size: usize) -> Result<u64, Exception> {
+        if !matches!(size, 8 | 16 | 32 | 64) {
+            return Err(Exception::IllegalSizeArgument(size))
+        }
+        let address = base.checked_add(disp).ok_or(Exception::AddressNotInMemoryBounds(base))?;
+        match address.checked_add(size as u64 / 8) {
+            Some(end) if address >= MEMORY_START_ADDRESS && end <= self.memory_end() => Ok(address),
+            _ => Err(Exception::AddressNotInMemoryBounds(address))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+    use crate::hardware::encode::{
+        encode_absolute, encode_add, encode_bare, encode_binary, encode_cas, encode_cmov, encode_exit, encode_jmc, encode_jmci, encode_jump,
+        encode_lea, encode_load, encode_move, encode_mul, encode_set, encode_store, encode_sub, encode_ternary, encode_unary, encode_update
+    };
+
+    fn load(cpu: &mut AVMCpu, program: &[u64]) {
+        for (i, instr) in program.iter().enumerate() {
+            cpu.bus.write(MEMORY_START_ADDRESS + i as u64 * INSTRUCTION_SIZE, *instr, 64).unwrap();
+        }
+    }
+
+    #[test]
+    fn halt_returns_control_to_caller() {
+        let mut cpu = AVMCpu::new();
+        let program = [
+            encode_set(1, 5),
+            encode_move(1, 2),
+            encode_bare(Opcode::Halt),
+        ];
+        let mut stopped = None;
+        for instr in program {
+            if let Err(e) = cpu.execute_instr(instr) {
+                stopped = Some(e);
+                break
+            }
+        }
+        assert_eq!(stopped, Some(Exception::Halt(0)));
+        assert_eq!(cpu.registers[2], 5);
+    }
+
+    #[test]
+    fn exit_carries_exit_code() {
+        let mut cpu = AVMCpu::new();
+        assert_eq!(cpu.execute_instr(encode_exit(7)), Err(Exception::Halt(7)));
+    }
+
+    #[test]
+    fn run_executes_program_to_completion() {
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[
+            encode_set(1, 5),
+            encode_set(2, 7),
+            encode_add(1, 2, 3),
+            encode_exit(3),
+        ]);
+        assert_eq!(cpu.run(), Ok(3));
+        assert_eq!(cpu.registers[3], 12);
+        assert_eq!(cpu.ip, MEMORY_START_ADDRESS + 3 * INSTRUCTION_SIZE);
+    }
+
+    #[test]
+    fn faults_report_the_faulting_instruction() {
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[
+            encode_set(1, 0x1000_1000),
+            encode_store(2, 12, 1, 0),
+        ]);
+        let fault = cpu.run().unwrap_err();
+        assert_eq!(fault, Exception::Fault {
+            ip: MEMORY_START_ADDRESS + INSTRUCTION_SIZE,
+            cause: Box::new(Exception::IllegalSizeArgument(12))
+        });
+        assert_eq!(fault.to_string(), "Size 12 is not allowed. Size can be one of that: 8, 16, 32, 64 (at instruction 10000008)");
+    }
+
+    #[test]
+    fn out_of_range_register_fields_fault_instead_of_panicking() {
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[
+            encode_add(1, 2, 255),
+        ]);
+        assert_eq!(cpu.run(), Err(Exception::Fault {
+            ip: MEMORY_START_ADDRESS,
+            cause: Box::new(Exception::IllegalRegisterIndex(0xFF))
+        }));
+        assert_eq!(cpu.execute_instr(encode_ternary(Opcode::Move, 1, 32, 255)), Err(Exception::IllegalRegisterIndex(32)));
+    }
+
+    #[test]
+    fn reset_restores_the_initial_state() {
+        let mut cpu = AVMCpu::with_memory_size(0x2000);
+        load(&mut cpu, &[
+            encode_set(1, 7),
+            encode_unary(Opcode::Push, 1),
+            encode_binary(Opcode::Cmp, 1, 0),
+            encode_bare(Opcode::Halt),
+        ]);
+        cpu.bus.protect(MEMORY_START_ADDRESS..MEMORY_START_ADDRESS + 32);
+        assert_eq!(cpu.run(), Ok(0));
+        cpu.reset();
+        let fresh = AVMCpu::with_memory_size(0x2000);
+        assert_eq!((cpu.registers, cpu.ip, cpu.flags), (fresh.registers, fresh.ip, fresh.flags));
+        assert!(cpu.bus.memory().written_pages().is_empty());
+        assert_eq!(cpu.bus.read(MEMORY_START_ADDRESS, 64), Ok(0));
+        assert!(cpu.bus.write(MEMORY_START_ADDRESS, 1, 64).is_ok());
+    }
+
+    #[test]
+    fn instruction_budget_stops_runaway_loops() {
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[encode_jump(Opcode::Jmp, 0x1000_0000)]);
+        cpu.set_instruction_budget(Some(100));
+        assert_eq!(cpu.run(), Err(Exception::InstructionBudgetExceeded(100)));
+
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[encode_bare(Opcode::Nop), encode_bare(Opcode::Halt)]);
+        cpu.set_instruction_budget(Some(2));
+        assert_eq!(cpu.run(), Ok(0));
+    }
+
+    #[test]
+    fn step_executes_one_instruction_at_a_time() {
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[
+            encode_set(4, 9),
+            encode_exit(1),
+        ]);
+        assert_eq!(cpu.step(), Ok(StepOutcome::Running));
+        assert_eq!(cpu.register(4), Ok(9));
+        assert_eq!(cpu.ip(), MEMORY_START_ADDRESS + INSTRUCTION_SIZE);
+        assert_eq!(cpu.step(), Ok(StepOutcome::Halted(1)));
+        assert_eq!(cpu.ip(), MEMORY_START_ADDRESS + INSTRUCTION_SIZE);
+    }
+
+    #[test]
+    fn trace_hook_sees_every_executed_instruction() {
+        let mut cpu = AVMCpu::new();
+        let program = [
+            encode_set(1, 5),
+            encode_move(1, 2),
+            encode_bare(Opcode::Halt),
+        ];
+        load(&mut cpu, &program);
+        let trace = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&trace);
+        cpu.set_trace_hook(Some(Box::new(move |ip, instr| sink.borrow_mut().push((ip, instr)))));
+        assert_eq!(cpu.run(), Ok(0));
+        assert_eq!(*trace.borrow(), vec![
+            (MEMORY_START_ADDRESS, program[0]),
+            (MEMORY_START_ADDRESS + INSTRUCTION_SIZE, program[1]),
+            (MEMORY_START_ADDRESS + 2 * INSTRUCTION_SIZE, program[2]),
+        ]);
+    }
+
+    #[test]
+    fn checkpoint_resumes_identically_across_serialization() {
+        let program = [
+            encode_set(1, 5),
+            encode_set(2, 7),
+            encode_add(1, 2, 3),
+            encode_mul(3, 3, 4),
+            encode_sub(4, 1, 5),
+            encode_exit(9),
+        ];
+        let data = MEMORY_START_ADDRESS + 0x2000;
+
+        let mut uninterrupted = AVMCpu::new();
+        load(&mut uninterrupted, &program);
+        uninterrupted.bus.write(data, 0xFEED, 64).unwrap();
+        assert_eq!(uninterrupted.run(), Ok(9));
+
+        let mut paused = AVMCpu::new();
+        load(&mut paused, &program);
+        paused.bus.write(data, 0xFEED, 64).unwrap();
+        assert_eq!(paused.run_for(3), Ok(RunOutcome::OutOfFuel));
+        let bytes = paused.snapshot().to_bytes();
+        drop(paused);
+
+        let mut resumed = AVMCpu::resume(&bytes).unwrap();
+        assert_eq!(resumed.run_for(100), Ok(RunOutcome::Halted(9)));
+        assert_eq!(resumed.registers, uninterrupted.registers);
+        assert_eq!(resumed.ip, uninterrupted.ip);
+        assert_eq!(resumed.bus.read(data, 64), Ok(0xFEED));
+    }
+
+    #[test]
+    fn resume_keeps_an_unaligned_memory_size() {
+        let size = 0x1808;
+        let mut cpu = AVMCpu::with_memory_size(size);
+        cpu.bus.write(MEMORY_START_ADDRESS + size - 8, 0xFEED, 64).unwrap();
+        let bytes = cpu.snapshot().to_bytes();
+
+        let resumed = AVMCpu::resume(&bytes).unwrap();
+        assert_eq!(resumed.bus.memory().size(), size);
+        assert_eq!(resumed.registers[13], MEMORY_START_ADDRESS + size);
+        assert_eq!(resumed.snapshot(), cpu.snapshot());
+    }
+
+    #[test]
+    fn restore_rewinds_registers_and_memory() {
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[
+            encode_set(1, 0x2000),
+            encode_set(2, 0xFF),
+            encode_set(3, 0x1000_0000),
+            encode_add(1, 3, 1),
+            encode_store(2, 64, 1, 0x10),
+            encode_binary(Opcode::Cmp, 1, 2),
+            encode_bare(Opcode::Halt),
+        ]);
+        assert_eq!(cpu.run_for(2), Ok(RunOutcome::OutOfFuel));
+        let snapshot = cpu.snapshot();
+        assert_eq!(cpu.run(), Ok(0));
+        assert_eq!(cpu.bus.read(MEMORY_START_ADDRESS + 0x2010, 64), Ok(0xFF));
+        assert_ne!(cpu.snapshot(), snapshot);
 
+        cpu.restore(&snapshot).unwrap();
+        assert_eq!(cpu.snapshot(), snapshot);
+        assert_eq!(cpu.bus.read(MEMORY_START_ADDRESS + 0x2010, 64), Ok(0));
+        assert_eq!(cpu.run(), Ok(0));
+        assert_eq!(cpu.bus.read(MEMORY_START_ADDRESS + 0x2010, 64), Ok(0xFF));
+    }
+
+    #[test]
+    fn accessors_expose_state_set_by_bytecode() {
+        let mut cpu = AVMCpu::new();
+        cpu.execute_instr(encode_set(4, 0x2A)).unwrap();
+        assert_eq!(cpu.register(4), Ok(42));
+        assert_eq!(cpu.registers()[4], 42);
+        assert_eq!(cpu.register(32), Err(Exception::IllegalRegisterIndex(32)));
+        assert_eq!(cpu.ip(), MEMORY_START_ADDRESS + INSTRUCTION_SIZE);
+    }
+
+    #[test]
+    fn base_relative_move_round_trips() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = MEMORY_START_ADDRESS + 0x1000;
+        cpu.registers[2] = 0xCAFE;
+        cpu.execute_instr(encode_store(2, 64, 1, 0x10)).unwrap();
+        cpu.execute_instr(encode_load(3, 64, 1, 0x10)).unwrap();
+        assert_eq!(cpu.registers[3], 0xCAFE);
+        assert_eq!(cpu.bus.read(MEMORY_START_ADDRESS + 0x1010, 64), Ok(0xCAFE));
+    }
+
+    #[test]
+    fn displacement_overflowing_u64_is_out_of_bounds() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = u64::MAX - 4;
+        assert_eq!(
+            cpu.execute_instr(encode_load(3, 64, 1, 0x10)),
+            Err(Exception::AddressNotInMemoryBounds(u64::MAX - 4))
+        );
+    }
+
+    #[test]
+    fn displacement_past_top_of_memory_is_out_of_bounds() {
+        let mut cpu = AVMCpu::new();
+        let last_word = MEMORY_START_ADDRESS + MEMORY_SIZE - 8;
+        cpu.registers[1] = last_word - 1;
+        assert_eq!(cpu.execute_instr(encode_load(3, 64, 1, 1)), Ok(()));
+        assert_eq!(
+            cpu.execute_instr(encode_load(3, 64, 1, 2)),
+            Err(Exception::AddressNotInMemoryBounds(last_word + 1))
+        );
+    }
+
+    #[test]
+    fn bitwise_operations() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 0xF0;
+        cpu.registers[2] = 0x0F;
+        cpu.execute_instr(encode_ternary(Opcode::And, 1, 2, 3)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Or, 1, 2, 4)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Xor, 1, 1, 5)).unwrap();
+        cpu.execute_instr(encode_binary(Opcode::Not, 5, 6)).unwrap();
+        assert_eq!(cpu.registers[3], 0);
+        assert_eq!(cpu.registers[4], 0xFF);
+        assert_eq!(cpu.registers[5], 0);
+        assert_eq!(cpu.registers[6], !0u64);
+    }
+
+    #[test]
+    fn signed_division_truncates_toward_zero() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = -7i64 as u64;
+        cpu.registers[2] = 2;
+        cpu.execute_instr(encode_ternary(Opcode::Sdiv, 1, 2, 3)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Srem, 1, 2, 4)).unwrap();
+        assert_eq!((cpu.registers[3] as i64, cpu.registers[4] as i64), (-3, -1));
+        cpu.registers[2] = 0;
+        assert_eq!(cpu.execute_instr(encode_ternary(Opcode::Sdiv, 1, 2, 3)), Err(Exception::DivisionByZero));
+        assert_eq!(cpu.execute_instr(encode_ternary(Opcode::Div, 1, 2, 3)), Err(Exception::DivisionByZero));
+    }
+
+    #[test]
+    fn signed_conditions_compare_as_i64() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = -1i64 as u64;
+        cpu.registers[2] = 1;
+        cpu.execute_instr(encode_jmc(2, 1, 2, 0x1000_1000)).unwrap();
+        assert_eq!(cpu.ip, MEMORY_START_ADDRESS + 0x1000);
+        cpu.execute_instr(encode_jmc(0xA, 1, 2, 0x1000_2000)).unwrap(); // u64::MAX > 1
+        assert_eq!(cpu.ip, MEMORY_START_ADDRESS + 0x2000);
+        cpu.execute_instr(encode_jmc(1, 1, 2, 0x1000_3000)).unwrap(); // not taken
+        assert_eq!(cpu.ip, MEMORY_START_ADDRESS + 0x2000 + INSTRUCTION_SIZE);
+    }
+
+    #[test]
+    fn neg_and_abs_work_on_twos_complement() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 5;
+        cpu.execute_instr(encode_binary(Opcode::Neg, 1, 2)).unwrap();
+        assert_eq!(cpu.registers[2], -5i64 as u64);
+        cpu.execute_instr(encode_binary(Opcode::Abs, 2, 3)).unwrap();
+        assert_eq!(cpu.registers[3], 5);
+        cpu.registers[4] = i64::MIN as u64;
+        cpu.execute_instr(encode_binary(Opcode::Abs, 4, 5)).unwrap();
+        assert_eq!(cpu.registers[5], i64::MIN as u64);
+    }
+
+    #[test]
+    fn inc_and_dec_wrap_around() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = u64::MAX;
+        cpu.execute_instr(encode_unary(Opcode::Inc, 1)).unwrap();
+        assert_eq!(cpu.registers[1], 0);
+        cpu.execute_instr(encode_unary(Opcode::Dec, 1)).unwrap();
+        assert_eq!(cpu.registers[1], u64::MAX);
+        cpu.execute_instr(encode_unary(Opcode::Dec, 1)).unwrap();
+        assert_eq!(cpu.registers[1], u64::MAX - 1);
+    }
+
+    #[test]
+    fn syscall_dispatches_to_registered_handler() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 20;
+        cpu.register_syscall(1, Box::new(|cpu| {
+            let argument = cpu.register(1).unwrap();
+            cpu.set_register(0, argument + 22).unwrap();
+        }));
+        cpu.execute_instr(encode_jump(Opcode::Syscall, 1)).unwrap();
+        assert_eq!(cpu.registers[0], 42);
+        assert_eq!(cpu.ip, MEMORY_START_ADDRESS + INSTRUCTION_SIZE);
+        cpu.execute_instr(encode_jump(Opcode::Syscall, 1)).unwrap(); // handlers stay registered
+        assert_eq!(cpu.execute_instr(encode_jump(Opcode::Syscall, 2)), Err(Exception::UnknownSyscall(2)));
+    }
+
+    #[test]
+    fn checked_arithmetic_reports_overflow_in_flags() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = u64::MAX;
+        cpu.registers[2] = 1;
+        cpu.execute_instr(encode_ternary(Opcode::Addc, 1, 2, 3)).unwrap();
+        assert_eq!(cpu.registers[3], 0);
+        assert_eq!(cpu.flags, FLAG_ZERO | FLAG_CARRY);
+        cpu.registers[1] = i64::MAX as u64;
+        cpu.execute_instr(encode_ternary(Opcode::Addc, 1, 2, 3)).unwrap();
+        assert_eq!(cpu.flags, FLAG_SIGN | FLAG_OVERFLOW);
+        cpu.registers[2] = 2;
+        cpu.execute_instr(encode_ternary(Opcode::Mulc, 1, 2, 3)).unwrap();
+        assert_eq!(cpu.registers[3], u64::MAX - 1);
+        assert_eq!(cpu.flags, FLAG_SIGN | FLAG_OVERFLOW);
+        cpu.registers[1] = 3;
+        cpu.execute_instr(encode_ternary(Opcode::Mulc, 1, 2, 3)).unwrap();
+        assert_eq!((cpu.registers[3], cpu.flags), (6, 0));
+    }
+
+    #[test]
+    fn adc_propagates_the_carry_between_words() {
+        let mut cpu = AVMCpu::new();
+        // 0x1_FFFFFFFFFFFFFFFF + 0x2_0000000000000001 as two-word values, low words in r1/r3, high words in r2/r4
+        cpu.registers[1] = u64::MAX;
+        cpu.registers[2] = 1;
+        cpu.registers[3] = 1;
+        cpu.registers[4] = 2;
+        cpu.execute_instr(encode_ternary(Opcode::Adc, 1, 3, 5)).unwrap();
+        assert_eq!((cpu.registers[5], cpu.flags & FLAG_CARRY), (0, FLAG_CARRY));
+        cpu.execute_instr(encode_ternary(Opcode::Adc, 2, 4, 6)).unwrap();
+        assert_eq!((cpu.registers[6], cpu.flags & FLAG_CARRY), (4, 0));
+        cpu.registers[7] = i64::MAX as u64;
+        cpu.flags = FLAG_CARRY;
+        cpu.execute_instr(encode_ternary(Opcode::Adc, 7, 0, 8)).unwrap();
+        assert_eq!((cpu.registers[8], cpu.flags), (1 << 63, FLAG_SIGN | FLAG_OVERFLOW));
+    }
+
+    #[test]
+    fn small_memory_faults_above_its_size() {
+        let mut cpu = AVMCpu::with_memory_size(0x1000);
+        assert_eq!(cpu.registers[13], MEMORY_START_ADDRESS + 0x1000);
+        cpu.registers[1] = MEMORY_START_ADDRESS + 0xFF8;
+        cpu.registers[2] = 7;
+        cpu.execute_instr(encode_store(2, 64, 1, 0)).unwrap();
+        assert_eq!(
+            cpu.execute_instr(encode_store(2, 64, 1, 8)),
+            Err(Exception::AddressNotInMemoryBounds(MEMORY_START_ADDRESS + 0x1000))
+        );
+        assert_eq!(cpu.bus.read(MEMORY_START_ADDRESS + 0x1000, 8), Err(Exception::AddressNotInMemoryBounds(MEMORY_START_ADDRESS + 0x1000)));
+        assert_eq!(cpu.execute_instr(encode_jump(Opcode::Jmp, 0x1000_1000)), Err(Exception::AddressNotInMemoryBounds(MEMORY_START_ADDRESS + 0x1000)));
+    }
+
+    #[test]
+    fn absolute_moves_report_bus_faults() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 0xABCD;
+        cpu.execute_instr(encode_absolute(Opcode::StoreAbsolute, 1, 64, 0x1000_0100)).unwrap();
+        cpu.execute_instr(encode_absolute(Opcode::LoadAbsolute, 2, 64, 0x1000_0100)).unwrap();
+        assert_eq!(cpu.registers[2], 0xABCD);
+        cpu.bus.protect(MEMORY_START_ADDRESS..MEMORY_START_ADDRESS + 0x100);
+        assert_eq!(cpu.execute_instr(encode_absolute(Opcode::StoreAbsolute, 1, 64, 0x1000_0000)), Err(Exception::WriteToReadOnlyMemory(0x1000_0000)));
+        assert_eq!(cpu.execute_instr(encode_absolute(Opcode::LoadAbsolute, 2, 64, 0x100)), Err(Exception::AddressNotInMemoryBounds(0x100)));
+        assert_eq!(cpu.execute_instr(encode_absolute(Opcode::LoadAbsolute, 2, 12, 0x1000_0100)), Err(Exception::IllegalSizeArgument(12)));
+    }
+
+        #[test]
+    fn lea_loads_an_address_usable_by_move() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[2] = 0xABCD;
+        cpu.execute_instr(encode_lea(1, 0x1000_0100)).unwrap();
+        assert_eq!(cpu.registers[1], 0x1000_0100);
+        cpu.execute_instr(encode_store(2, 64, 1, 8)).unwrap();
+        cpu.execute_instr(encode_load(3, 64, 1, 8)).unwrap();
+        assert_eq!(cpu.registers[3], 0xABCD);
+        assert_eq!(
+            cpu.execute_instr(encode_lea(1, 0x100)),
+            Err(Exception::AddressNotInMemoryBounds(0x100))
+        );
+        assert_eq!(cpu.registers[1], 0x1000_0100);
+    }
+
+    #[test]
+    fn cmov_moves_only_when_the_condition_holds() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 5;
+        cpu.registers[2] = 3;
+        cpu.registers[3] = 42;
+        cpu.registers[4] = 7;
+        cpu.execute_instr(encode_cmov(0xA, 1, 2, 3, 4)).unwrap();
+        assert_eq!(cpu.registers[4], 42);
+        cpu.registers[4] = 7;
+        cpu.execute_instr(encode_cmov(0xB, 1, 2, 3, 4)).unwrap();
+        assert_eq!(cpu.registers[4], 7);
+        assert_eq!(cpu.ip, MEMORY_START_ADDRESS + 2 * INSTRUCTION_SIZE);
+        assert_eq!(cpu.execute_instr(encode_cmov(0, 1, 2, 3, 4)), Err(Exception::UnexpectedCondition(0)));
+    }
+
+    #[test]
+    fn addm_updates_memory_in_place() {
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[
+            encode_lea(1, 0x1000_1000),
+            encode_set(2, 1),
+            encode_set(4, 5),
+            encode_update(Opcode::Addm, 2, 64, 1, 0),
+            encode_unary(Opcode::Inc, 3),
+            encode_jmc(0xB, 3, 4, 0x1000_0018),
+            encode_exit(0),
+        ]);
+        assert_eq!(cpu.run(), Ok(0));
+        assert_eq!(cpu.bus.read(0x1000_1000, 64), Ok(5));
+
+        cpu.execute_instr(encode_update(Opcode::Subm, 4, 8, 1, 0)).unwrap();
+        assert_eq!(cpu.bus.read(0x1000_1000, 64), Ok(0));
+        cpu.execute_instr(encode_update(Opcode::Subm, 2, 8, 1, 0)).unwrap();
+        assert_eq!(cpu.bus.read(0x1000_1000, 64), Ok(0xFF));
+        assert_eq!(cpu.execute_instr(encode_update(Opcode::Addm, 2, 12, 1, 0)), Err(Exception::IllegalSizeArgument(12)));
+    }
+
+    #[test]
+    fn cas_swaps_only_the_expected_value() {
+        let mut cpu = AVMCpu::new();
+        let address = MEMORY_START_ADDRESS + 0x1000;
+        cpu.bus.write(address, 5, 64).unwrap();
+        cpu.registers[1] = address;
+        cpu.registers[2] = 5;
+        cpu.registers[3] = 9;
+        cpu.execute_instr(encode_cas(1, 2, 3, 4)).unwrap();
+        assert_eq!((cpu.registers[4], cpu.bus.read(address, 64)), (1, Ok(9)));
+        cpu.registers[3] = 11;
+        cpu.execute_instr(encode_cas(1, 2, 3, 4)).unwrap();
+        assert_eq!((cpu.registers[4], cpu.bus.read(address, 64)), (0, Ok(9)));
+    }
+
+    #[test]
+    fn swap_exchanges_two_registers() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 11;
+        cpu.registers[2] = 22;
+        cpu.execute_instr(encode_binary(Opcode::Swap, 1, 2)).unwrap();
+        assert_eq!((cpu.registers[1], cpu.registers[2]), (22, 11));
+        cpu.execute_instr(encode_binary(Opcode::Swap, 1, 1)).unwrap();
+        assert_eq!(cpu.registers[1], 22);
+    }
+
+    #[test]
+    fn memcpy_copies_overlapping_ranges() {
+        let mut cpu = AVMCpu::new();
+        let base = MEMORY_START_ADDRESS + 0x1000;
+        cpu.bus.write(base, 0x0807_0605_0403_0201, 64).unwrap();
+        cpu.registers[1] = base + 0x100;
+        cpu.registers[2] = base;
+        cpu.registers[3] = 8;
+        cpu.execute_instr(encode_ternary(Opcode::Memcpy, 1, 2, 3)).unwrap();
+        assert_eq!(cpu.bus.read(base + 0x100, 64), Ok(0x0807_0605_0403_0201));
+
+        cpu.registers[1] = base + 2;
+        cpu.execute_instr(encode_ternary(Opcode::Memcpy, 1, 2, 3)).unwrap(); // overlapping, destination above source
+        let bytes: Vec<u64> = (0..10).map(|i| cpu.bus.read(base + i, 8).unwrap()).collect();
+        assert_eq!(bytes, [1, 2, 1, 2, 3, 4, 5, 6, 7, 8]);
+
+        cpu.registers[1] = MEMORY_START_ADDRESS + MEMORY_SIZE - 4;
+        assert_eq!(cpu.execute_instr(encode_ternary(Opcode::Memcpy, 1, 2, 3)), Err(Exception::AddressNotInMemoryBounds(MEMORY_START_ADDRESS + MEMORY_SIZE - 4)));
+    }
+
+    #[test]
+    fn shift_operations() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 1;
+        cpu.registers[2] = 4;
+        cpu.registers[3] = -8i64 as u64;
+        cpu.registers[4] = 1;
+        cpu.registers[5] = 68;
+        cpu.execute_instr(encode_ternary(Opcode::Shl, 1, 2, 6)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Sar, 3, 4, 7)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Shr, 3, 4, 8)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Shl, 1, 5, 9)).unwrap();
+        assert_eq!(cpu.registers[6], 16);
+        assert_eq!(cpu.registers[7] as i64, -4);
+        assert_eq!(cpu.registers[8], (-8i64 as u64) >> 1);
+        assert_eq!(cpu.registers[9], 16);
+    }
+
+    #[test]
+    fn bit_counting_operations() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 0xFF;
+        cpu.registers[2] = 1;
+        cpu.execute_instr(encode_binary(Opcode::Popcnt, 1, 3)).unwrap();
+        cpu.execute_instr(encode_binary(Opcode::Clz, 2, 4)).unwrap();
+        cpu.execute_instr(encode_binary(Opcode::Ctz, 1, 5)).unwrap();
+        cpu.execute_instr(encode_binary(Opcode::Clz, 0, 6)).unwrap();
+        assert_eq!(cpu.registers[3..7], [8, 63, 0, 64]);
+    }
+
+    #[test]
+    fn bswap_reverses_the_byte_order() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 0x0102_0304_0506_0708;
+        cpu.execute_instr(encode_binary(Opcode::Bswap, 1, 2)).unwrap();
+        assert_eq!(cpu.registers[2], 0x0807_0605_0403_0201);
+    }
+
+    #[test]
+    fn push_and_pop_round_trip() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 11;
+        cpu.registers[2] = 22;
+        cpu.execute_instr(encode_unary(Opcode::Push, 1)).unwrap();
+        cpu.execute_instr(encode_unary(Opcode::Push, 2)).unwrap();
+        assert_eq!(cpu.registers[13], cpu.registers[12] - 16);
+        cpu.execute_instr(encode_unary(Opcode::Pop, 3)).unwrap();
+        cpu.execute_instr(encode_unary(Opcode::Pop, 4)).unwrap();
+        assert_eq!((cpu.registers[3], cpu.registers[4]), (22, 11));
+        assert_eq!(cpu.registers[13], cpu.registers[12]);
+    }
+
+    #[test]
+    fn stack_underflow_and_overflow_are_detected() {
+        let mut cpu = AVMCpu::new();
+        let base = cpu.registers[12];
+        assert_eq!(cpu.execute_instr(encode_unary(Opcode::Pop, 1)), Err(Exception::StackUnderflow(base)));
+        cpu.registers[13] = base - STACK_SIZE;
+        assert_eq!(cpu.execute_instr(encode_unary(Opcode::Push, 1)), Err(Exception::StackOverflow(base - STACK_SIZE)));
+    }
+
+    #[test]
+    fn fuel_reports_remaining_budget() {
+        let program = [
+            encode_unary(Opcode::Fuel, 1),
+            encode_unary(Opcode::Fuel, 2),
+            encode_bare(Opcode::Halt),
+        ];
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &program);
+        assert_eq!(cpu.run_for(10), Ok(RunOutcome::Halted(0)));
+        assert_eq!((cpu.registers[1], cpu.registers[2]), (9, 8));
+
+        let mut unbounded = AVMCpu::new();
+        load(&mut unbounded, &program);
+        assert_eq!(unbounded.run(), Ok(0));
+        assert_eq!(unbounded.registers[1], u64::MAX);
+    }
+
+    #[test]
+    fn call_returns_after_call_site() {
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[
+            encode_jump(Opcode::Call, 0x1000_0018),
+            encode_set(2, 1),
+            encode_bare(Opcode::Halt),
+            encode_set(1, 7),
+            encode_bare(Opcode::Ret),
+        ]);
+        assert_eq!(cpu.run(), Ok(0));
+        assert_eq!((cpu.registers[1], cpu.registers[2]), (7, 1));
+        assert_eq!(cpu.registers[13], cpu.registers[12]);
+    }
+
+    #[test]
+    fn ret_with_empty_stack_underflows() {
+        let mut cpu = AVMCpu::new();
+        assert_eq!(cpu.execute_instr(encode_bare(Opcode::Ret)), Err(Exception::StackUnderflow(cpu.registers[12])));
+    }
+
+    #[test]
+    fn float_arithmetic_on_register_bits() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 1.5f64.to_bits();
+        cpu.registers[2] = 2.5f64.to_bits();
+        cpu.registers[3] = 0f64.to_bits();
+        cpu.execute_instr(encode_ternary(Opcode::Fadd, 1, 2, 4)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Fsub, 1, 2, 5)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Fmul, 1, 2, 6)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Fdiv, 1, 3, 7)).unwrap();
+        cpu.execute_instr(encode_ternary(Opcode::Fdiv, 3, 3, 8)).unwrap();
+        assert_eq!(f64::from_bits(cpu.registers[4]), 4.0);
+        assert_eq!(f64::from_bits(cpu.registers[5]), -1.0);
+        assert_eq!(f64::from_bits(cpu.registers[6]), 3.75);
+        assert_eq!(f64::from_bits(cpu.registers[7]), f64::INFINITY);
+        assert!(f64::from_bits(cpu.registers[8]).is_nan());
+    }
+
+    #[test]
+    fn int_float_conversions() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 3;
+        cpu.registers[2] = 3.7f64.to_bits();
+        cpu.registers[3] = (-3.7f64).to_bits();
+        cpu.registers[4] = f64::NAN.to_bits();
+        cpu.registers[5] = 1e300f64.to_bits();
+        cpu.execute_instr(encode_binary(Opcode::Itof, 1, 6)).unwrap();
+        cpu.execute_instr(encode_binary(Opcode::Ftoi, 2, 7)).unwrap();
+        cpu.execute_instr(encode_binary(Opcode::Ftoi, 3, 8)).unwrap();
+        cpu.execute_instr(encode_binary(Opcode::Ftoi, 4, 9)).unwrap();
+        cpu.execute_instr(encode_binary(Opcode::Ftoi, 5, 10)).unwrap();
+        assert_eq!(f64::from_bits(cpu.registers[6]), 3.0);
+        assert_eq!(cpu.registers[7], 3);
+        assert_eq!(cpu.registers[8] as i64, -3);
+        assert_eq!(cpu.registers[9], 0);
+        assert_eq!(cpu.registers[10] as i64, i64::MAX);
+    }
+
+    #[test]
+    fn set_loads_full_width_immediates() {
+        let mut cpu = AVMCpu::new();
+        cpu.execute_instr(encode_set(1, 0x1234)).unwrap();
+        cpu.execute_instr(encode_set(2, 0xDEAD_BEEF)).unwrap();
+        cpu.execute_instr(encode_set(3, 0xFF_FFFF_FFFF)).unwrap();
+        assert_eq!(cpu.registers[1], 0x1234);
+        assert_eq!(cpu.registers[2], 0xDEADBEEF);
+        assert_eq!(cpu.registers[3], 0xFF_FFFF_FFFF);
+    }
+
+    #[test]
+    fn jumps_land_on_full_width_addresses() {
+        let mut cpu = AVMCpu::new();
+        cpu.execute_instr(encode_jump(Opcode::Jmp, 0x1234_5678)).unwrap();
+        assert_eq!(cpu.ip, 0x12345678);
+        cpu.registers[1] = 2;
+        cpu.registers[2] = 1;
+        cpu.execute_instr(encode_jmc(0xA, 1, 2, 0x3FFF_FFF8)).unwrap();
+        assert_eq!(cpu.ip, 0x3FFFFFF8);
+        assert_eq!(
+            cpu.execute_instr(encode_jump(Opcode::Jmp, 0x40)),
+            Err(Exception::AddressNotInMemoryBounds(0x40))
+        );
+        assert_eq!(cpu.ip, 0x3FFFFFF8);
+    }
+
+    #[test]
+    fn callr_calls_through_a_register() {
+        let mut cpu = AVMCpu::new();
+        load(&mut cpu, &[
+            encode_lea(1, 0x1000_0018),
+            encode_unary(Opcode::Callr, 1),
+            encode_exit(0),
+            encode_set(2, 7),
+            encode_bare(Opcode::Ret),
+        ]);
+        assert_eq!(cpu.run(), Ok(0));
+        assert_eq!(cpu.registers[2], 7);
+        assert_eq!(cpu.registers[13], cpu.registers[12]);
+
+        cpu.registers[1] = 0x100;
+        assert_eq!(cpu.execute_instr(encode_unary(Opcode::Callr, 1)), Err(Exception::AddressNotInMemoryBounds(0x100)));
+    }
+
+    #[test]
+    fn float_conditions_compare_as_f64() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = (-1.0f64).to_bits();
+        cpu.registers[2] = 1.0f64.to_bits();
+        cpu.execute_instr(encode_jmc(0xB, 1, 2, 0x1000_1000)).unwrap(); // not taken as u64
+        assert_eq!(cpu.ip, MEMORY_START_ADDRESS + INSTRUCTION_SIZE);
+        cpu.execute_instr(encode_jmc(6, 1, 2, 0x1000_1000)).unwrap();
+        assert_eq!(cpu.ip, 0x1000_1000);
+
+        assert_eq!(AVMCpu::condition_holds(0x7, (-0.0f64).to_bits(), 0.0f64.to_bits()), Ok(true));
+        assert_eq!(AVMCpu::condition_holds(0x5, f64::INFINITY.to_bits(), f64::MAX.to_bits()), Ok(true));
+        for code in 0x5..=0x9 {
+            assert_eq!(AVMCpu::condition_holds(code, f64::NAN.to_bits(), f64::NAN.to_bits()), Ok(false));
+            assert_eq!(AVMCpu::condition_holds(code, 1.0f64.to_bits(), f64::NAN.to_bits()), Ok(false));
         }
     }
+
+    #[test]
+    fn jmci_compares_a_register_with_an_immediate() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 11;
+        cpu.execute_instr(encode_jmci(0xA, 1, 10, 0x1000_1000)).unwrap();
+        assert_eq!(cpu.ip, 0x1000_1000);
+        cpu.registers[1] = 10;
+        cpu.execute_instr(encode_jmci(0xA, 1, 10, 0x1000_2000)).unwrap(); // not taken
+        assert_eq!(cpu.ip, 0x1000_1000 + INSTRUCTION_SIZE);
+    }
+
+    #[test]
+    fn jmc_not_equal_branches_or_falls_through() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 1;
+        cpu.registers[2] = 2;
+        cpu.execute_instr(encode_jmc(0xF, 1, 2, 0x1000_0040)).unwrap();
+        assert_eq!(cpu.ip, 0x10000040);
+        cpu.registers[2] = 1;
+        cpu.execute_instr(encode_jmc(0xF, 1, 2, 0x1000_0080)).unwrap();
+        assert_eq!(cpu.ip, 0x10000040 + INSTRUCTION_SIZE);
+        assert_eq!(cpu.execute_instr(encode_jmc(0, 1, 2, 0x1000_0080)), Err(Exception::UnexpectedCondition(0)));
+    }
+
+    #[test]
+    fn nop_only_advances_ip() {
+        let mut cpu = AVMCpu::new();
+        let registers = cpu.registers;
+        cpu.execute_instr(encode_bare(Opcode::Nop)).unwrap();
+        assert_eq!(cpu.ip, MEMORY_START_ADDRESS + INSTRUCTION_SIZE);
+        assert_eq!(cpu.registers, registers);
+    }
+
+    #[test]
+    fn cmp_sets_flags_for_branches() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = 5;
+        cpu.registers[2] = 5;
+        cpu.registers[3] = 7;
+        cpu.execute_instr(encode_binary(Opcode::Cmp, 1, 2)).unwrap();
+        assert_eq!(cpu.flags, FLAG_ZERO);
+        cpu.execute_instr(encode_jump(Opcode::Jz, 0x1000_0040)).unwrap();
+        assert_eq!(cpu.ip, 0x10000040);
+        cpu.execute_instr(encode_jump(Opcode::Js, 0x1000_0080)).unwrap();
+        assert_eq!(cpu.ip, 0x10000048);
+
+        cpu.execute_instr(encode_binary(Opcode::Cmp, 1, 3)).unwrap();
+        assert_eq!(cpu.flags, FLAG_CARRY | FLAG_SIGN);
+        cpu.execute_instr(encode_jump(Opcode::Jnz, 0x1000_0080)).unwrap();
+        assert_eq!(cpu.ip, 0x10000080);
+        cpu.execute_instr(encode_jump(Opcode::Js, 0x1000_00C0)).unwrap();
+        assert_eq!(cpu.ip, 0x100000C0);
+    }
+
+    #[test]
+    fn cmp_detects_signed_overflow() {
+        let mut cpu = AVMCpu::new();
+        cpu.registers[1] = i64::MIN as u64;
+        cpu.registers[2] = 1;
+        cpu.execute_instr(encode_binary(Opcode::Cmp, 1, 2)).unwrap();
+        assert_eq!(cpu.flags, FLAG_OVERFLOW);
+    }
 }
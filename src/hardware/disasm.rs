@@ -0,0 +1,263 @@
+//! # AetherVM Disassembler
+//!
+//! This module turns encoded 64-bit instructions back into the textual mnemonics used in the bytecode comments of the
+//! CPU, e.g. `r1 $add r2 -> r3`. Decoding mirrors [AVMCpu::execute_instr](crate::hardware::cpu::AVMCpu::execute_instr)
+//! and shares its [Opcode] mapping, so the rendered operands are exactly what the CPU would use. Words that do not decode to a known opcode are rendered
+//! as a `.word` directive.
+//!
+//! Whole programs are rendered by [render_program] as an address-prefixed listing, similar to `objdump`.
+//!
+//! Output can be rendered plain or colorized with the `colored` crate. Colorized output highlights mnemonics, register
+//! operands and immediates differently and still honours `colored`'s global settings, such as the `NO_COLOR` variable.
+//!
+use colored::Colorize;
+use crate::hardware::cpu::INSTRUCTION_SIZE;
+use crate::hardware::encode::{decode, Instruction, Operands};
+use crate::hardware::opcode::Opcode;
+
+/// Disassembles a single instruction into plain text.
+///
+/// # Parameters
+/// - `instr`: The encoded instruction.
+///
+/// # Returns
+/// The instruction mnemonic with its operands, or a `.word` line for unknown opcodes.
+pub fn disassemble(instr: u64) -> String {
+    render(instr, &Palette { color: false })
+}
+
+/// Disassembles a single instruction into colorized text.
+///
+/// # Parameters
+/// - `instr`: The encoded instruction.
+///
+/// # Returns
+/// The same text as [disassemble], with ANSI colors applied to mnemonics, registers and immediates.
+pub fn disassemble_colored(instr: u64) -> String {
+    render(instr, &Palette { color: true })
+}
+
+/// Disassembles every word of a program into plain text.
+///
+/// # Parameters
+/// - `words`: The encoded instructions of the program.
+///
+/// # Returns
+/// One line per word, as produced by [disassemble].
+pub fn disassemble_all(words: &[u64]) -> Vec<String> {
+    words.iter().map(|&word| disassemble(word)).collect()
+}
+
+/// Renders a whole program as address-prefixed disassembly.
+///
+/// Each line holds the address of the word, the raw word in hex and its disassembly.
+/// Words that do not decode to a known opcode are rendered as `.word` directives.
+///
+/// # Parameters
+/// - `words`: The encoded instructions of the program.
+/// - `base`: The address of the first word.
+///
+/// # Returns
+/// The listing, one line per word, each terminated by a newline.
+pub fn render_program(words: &[u64], base: u64) -> String {
+    render_lines(words, base, &Palette { color: false })
+}
+
+/// Renders a whole program like [render_program], with ANSI colors applied.
+pub fn render_program_colored(words: &[u64], base: u64) -> String {
+    render_lines(words, base, &Palette { color: true })
+}
+
+fn render_lines(words: &[u64], base: u64, p: &Palette) -> String {
+    words.iter()
+        .enumerate()
+        .map(|(i, &word)| format!("{:08X}:  {:016X}  {}\n", base + i as u64 * INSTRUCTION_SIZE, word, render(word, p)))
+        .collect()
+}
+
+struct Palette {
+    color: bool
+}
+impl Palette {
+    fn mnemonic(&self, text: &str) -> String {
+        if self.color { text.truecolor(0, 255, 255).to_string() } else { text.to_string() }
+    }
+    fn register(&self, index: usize) -> String {
+        let text = format!("r{}", index);
+        if self.color { text.yellow().to_string() } else { text }
+    }
+    fn immediate(&self, text: String) -> String {
+        if self.color { text.magenta().to_string() } else { text }
+    }
+    fn invalid(&self, text: &str) -> String {
+        if self.color { text.red().to_string() } else { text.to_string() }
+    }
+}
+
+fn render(instr: u64, p: &Palette) -> String {
+    let Ok(Instruction { opcode, operands }) = decode(instr) else {
+        return format!("{} {}", p.invalid(".word"), p.immediate(format!("0x{:016X}", instr)))
+    };
+    let size = |size: usize| p.mnemonic(&format!("$i{}", size));
+    match (opcode, operands) {
+        (Opcode::Nop, _) => p.mnemonic("$nop"),
+        (Opcode::Ret, _) => p.mnemonic("$ret"),
+        (Opcode::Halt, _) => p.mnemonic("$halt"),
+        (Opcode::Set, Operands::Immediate { reg, value }) => format!("{} {} {}", p.register(reg), p.mnemonic("$set"), p.immediate(format!("0x{:X}", value))),
+        (Opcode::Lea, Operands::Immediate { reg, value }) => format!("{} {} {}", p.register(reg), p.mnemonic("$lea"), p.immediate(format!("@{:X}", value))),
+        (Opcode::LoadAbsolute, Operands::Absolute { reg, size: bits, address }) => format!("{} {} {} {}", p.immediate(format!("@{:X}", address)), p.mnemonic("$move"), p.register(reg), size(bits)),
+        (Opcode::StoreAbsolute, Operands::Absolute { reg, size: bits, address }) => format!("{} {} {} {}", p.register(reg), p.mnemonic("$move"), p.immediate(format!("@{:X}", address)), size(bits)),
+        (Opcode::Load, Operands::Memory { reg, size: bits, base, disp }) => format!("[{} + {}] {} {} {}", p.register(base), p.immediate(format!("0x{:X}", disp)), p.mnemonic("$move"), p.register(reg), size(bits)),
+        (Opcode::Store | Opcode::Addm | Opcode::Subm, Operands::Memory { reg, size: bits, base, disp }) => {
+            format!("{} {} [{} + {}] {}", p.register(reg), p.mnemonic(mnemonic(opcode)), p.register(base), p.immediate(format!("0x{:X}", disp)), size(bits))
+        }
+        (Opcode::Cas, Operands::CompareAndSwap { address, expected, new, result }) => format!("{} {} {} {} -> {}", p.register(address), p.mnemonic("$cas"), p.register(expected), p.register(new), p.register(result)),
+        (Opcode::Cmov, Operands::ConditionalMove { cond, lhs, rhs, src, dst }) => {
+            format!("{} {} {} {} {} -> {}", p.register(lhs), p.mnemonic("$cmov"), p.mnemonic(condition_symbol(cond)), p.register(rhs), p.register(src), p.register(dst))
+        }
+        (Opcode::Jmc, Operands::Branch { cond, lhs, rhs, address }) => {
+            format!("{} {} {} {} {}", p.register(lhs), p.mnemonic("$jmc"), p.mnemonic(condition_symbol(cond)), p.register(rhs), p.immediate(format!("@{:X}", address)))
+        }
+        (Opcode::Jmci, Operands::BranchImmediate { cond, reg, imm, address }) => {
+            format!("{} {} {} {} {}", p.register(reg), p.mnemonic("$jmci"), p.mnemonic(condition_symbol(cond)), p.immediate(imm.to_string()), p.immediate(format!("@{:X}", address)))
+        }
+        (_, Operands::Address(address)) => format!("{} {}", p.mnemonic(mnemonic(opcode)), p.immediate(format!("@{:X}", address))),
+        (_, Operands::Number(number)) => format!("{} {}", p.mnemonic(mnemonic(opcode)), p.immediate(format!("0x{:X}", number))),
+        (_, Operands::Code(code)) => format!("{} {}", p.mnemonic(mnemonic(opcode)), p.immediate(code.to_string())),
+        (Opcode::Move | Opcode::Cmp | Opcode::Swap, Operands::Registers { a, b, .. }) => format!("{} {} {}", p.register(a), p.mnemonic(mnemonic(opcode)), p.register(b)),
+        (Opcode::Itof | Opcode::Ftoi | Opcode::Not | Opcode::Neg | Opcode::Abs | Opcode::Popcnt | Opcode::Clz | Opcode::Ctz | Opcode::Bswap, Operands::Registers { a, b, .. }) => format!("{} {} -> {}", p.register(a), p.mnemonic(mnemonic(opcode)), p.register(b)),
+        (Opcode::Memcpy, Operands::Registers { a, b, c }) => format!("{} {} {} {}", p.register(a), p.mnemonic("$memcpy"), p.register(b), p.register(c)),
+        (Opcode::Inc | Opcode::Dec | Opcode::Push | Opcode::Pop | Opcode::Callr | Opcode::Fuel, Operands::Registers { a, .. }) => {
+            format!("{} {}", p.mnemonic(mnemonic(opcode)), p.register(a))
+        }
+        (_, Operands::Registers { a, b, c }) => format!("{} {} {} -> {}", p.register(a), p.mnemonic(mnemonic(opcode)), p.register(b), p.register(c)),
+        _ => format!("{} {}", p.invalid(".word"), p.immediate(format!("0x{:016X}", instr)))
+    }
+}
+
+/// Returns the mnemonic of an operation as written in source, e.g. `$add`.
+fn mnemonic(opcode: Opcode) -> &'static str {
+    match opcode {
+        Opcode::Nop => "$nop",
+        Opcode::Move | Opcode::LoadAbsolute | Opcode::StoreAbsolute | Opcode::Load | Opcode::Store => "$move",
+        Opcode::Set => "$set",
+        Opcode::Add => "$add",
+        Opcode::Sub => "$sub",
+        Opcode::Mul => "$mul",
+        Opcode::Div => "$div",
+        Opcode::Cmp => "$cmp",
+        Opcode::Memcpy => "$memcpy",
+        Opcode::Swap => "$swap",
+        Opcode::Sdiv => "$sdiv",
+        Opcode::Srem => "$srem",
+        Opcode::Inc => "$inc",
+        Opcode::Dec => "$dec",
+        Opcode::Addc => "$addc",
+        Opcode::Mulc => "$mulc",
+        Opcode::Adc => "$adc",
+        Opcode::Neg => "$neg",
+        Opcode::Abs => "$abs",
+        Opcode::Lea => "$lea",
+        Opcode::Cmov => "$cmov",
+        Opcode::Cas => "$cas",
+        Opcode::Addm => "$addm",
+        Opcode::Subm => "$subm",
+        Opcode::Fadd => "$fadd",
+        Opcode::Fsub => "$fsub",
+        Opcode::Fmul => "$fmul",
+        Opcode::Fdiv => "$fdiv",
+        Opcode::Itof => "$itof",
+        Opcode::Ftoi => "$ftoi",
+        Opcode::Push => "$push",
+        Opcode::Pop => "$pop",
+        Opcode::And => "$and",
+        Opcode::Or => "$or",
+        Opcode::Xor => "$xor",
+        Opcode::Not => "$not",
+        Opcode::Shl => "$shl",
+        Opcode::Shr => "$shr",
+        Opcode::Sar => "$sar",
+        Opcode::Popcnt => "$popcnt",
+        Opcode::Clz => "$clz",
+        Opcode::Ctz => "$ctz",
+        Opcode::Bswap => "$bswap",
+        Opcode::Jmp => "$jmp",
+        Opcode::Jmc => "$jmc",
+        Opcode::Call => "$call",
+        Opcode::Ret => "$ret",
+        Opcode::Jz => "$jz",
+        Opcode::Jnz => "$jnz",
+        Opcode::Js => "$js",
+        Opcode::Jns => "$jns",
+        Opcode::Jc => "$jc",
+        Opcode::Jnc => "$jnc",
+        Opcode::Jo => "$jo",
+        Opcode::Jno => "$jno",
+        Opcode::Jmci => "$jmci",
+        Opcode::Callr => "$callr",
+        Opcode::Fuel => "$fuel",
+        Opcode::Syscall => "$syscall",
+        Opcode::Exit => "$exit",
+        Opcode::Halt => "$halt"
+    }
+}
+
+/// Returns the symbol of a `$jmc` condition code, or `?` for unknown codes.
+fn condition_symbol(code: u64) -> &'static str {
+    match code {
+        0x1 => "s>",
+        0x2 => "s<",
+        0x3 => "s>=",
+        0x4 => "s<=",
+        0x5 => "f>",
+        0x6 => "f<",
+        0x7 => "f==",
+        0x8 => "f>=",
+        0x9 => "f<=",
+        0xA => ">",
+        0xB => "<",
+        0xC => "==",
+        0xD => ">=",
+        0xE => "<=",
+        0xF => "!=",
+        _ => "?"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::encode::{encode_add, encode_bare, encode_set};
+
+    #[test]
+    fn renders_mnemonics_and_unknown_words() {
+        assert_eq!(disassemble(encode_add(1, 2, 3)), "r1 $add r2 -> r3");
+        assert_eq!(disassemble(encode_set(4, 0x2A)), "r4 $set 0x2A");
+        assert_eq!(disassemble(encode_bare(Opcode::Halt)), "$halt");
+        assert_eq!(disassemble(0x1234_0000_0000_0000), ".word 0x1234000000000000");
+    }
+
+    #[test]
+    fn translated_programs_disassemble_to_their_source() {
+        let bytecode = bctranslator::translate("$funcdef main { $add r1, r2, r3 $set r4, 0x2A loop: $jmp loop $halt }").unwrap().code;
+        let words: Vec<u64> = bytecode.chunks(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect();
+        assert_eq!(disassemble_all(&words), ["r1 $add r2 -> r3", "r4 $set 0x2A", "$jmp @10000010", "$halt"]);
+    }
+
+    #[test]
+    fn translated_memory_updates_disassemble() {
+        let bytecode = bctranslator::translate("$funcdef main { $addm r1, 64, r2, 0x10 $subm r3, 8, r13, 0 }").unwrap().code;
+        let words: Vec<u64> = bytecode.chunks(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect();
+        assert_eq!(disassemble_all(&words), ["r1 $addm [r2 + 0x10] $i64", "r3 $subm [r13 + 0x0] $i8"]);
+    }
+
+    #[test]
+    fn renders_addressed_program() {
+        let listing = render_program(&[encode_set(1, 5), 0x1234_0000_0000_0000, encode_bare(Opcode::Halt)], 0x10000000);
+        assert_eq!(listing, "\
+10000000:  CD01010000000005  r1 $set 0x5
+10000008:  1234000000000000  .word 0x1234000000000000
+10000010:  FFFF000000000000  $halt
+");
+    }
+}
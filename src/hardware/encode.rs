@@ -0,0 +1,225 @@
+//! # AetherVM Instruction Encoding
+//!
+//! This module builds encoded 64-bit instructions and splits them back into their fields, so that the CPU, the
+//! disassembler and hand-assembled programs in tests share a single description of the instruction layout.
+//!
+//! The operation occupies bits 48..64. Register operands go into the byte fields at bits 40..48, 32..40 and 24..32 in
+//! the order the instruction reads them. Layouts that deviate from that are documented on
+//! [AVMCpu::execute_instr](crate::hardware::cpu::AVMCpu::execute_instr) and produced by the dedicated encoders below.
+//! The encoders wrap the assembler's in [bctranslator::encode], taking an [Opcode] instead of its raw value.
+//!
+use bctranslator::encode;
+use crate::hardware::exceptions::Exception;
+use crate::hardware::opcode::Opcode;
+
+/// A decoded instruction: its operation and the operands in the layout that operation uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// The operation in bits 48..64.
+    pub opcode: Opcode,
+    /// The operand fields, typed by the layout of the operation.
+    pub operands: Operands
+}
+impl Instruction {
+    /// Returns the register fields the operation actually reads or writes, in encoding order.
+    ///
+    /// Unused fields are left out, so stray bits in them do not count as register references.
+    pub fn registers(&self) -> Vec<usize> {
+        match self.operands {
+            Operands::Registers { a, b, c } => match self.opcode {
+                Opcode::Move | Opcode::Cmp | Opcode::Swap | Opcode::Itof | Opcode::Ftoi | Opcode::Not | Opcode::Neg | Opcode::Abs
+                | Opcode::Popcnt | Opcode::Clz | Opcode::Ctz | Opcode::Bswap => vec![a, b],
+                Opcode::Push | Opcode::Pop | Opcode::Inc | Opcode::Dec | Opcode::Fuel | Opcode::Callr => vec![a],
+                _ => vec![a, b, c]
+            },
+            Operands::Immediate { reg, .. } | Operands::Absolute { reg, .. } => vec![reg],
+            Operands::Memory { reg, base, .. } => vec![reg, base],
+            Operands::CompareAndSwap { address, expected, new, result } => vec![address, expected, new, result],
+            Operands::ConditionalMove { lhs, rhs, src, dst, .. } => vec![lhs, rhs, src, dst],
+            _ => Vec::new()
+        }
+    }
+}
+
+/// The operand fields of an instruction, one variant per operand layout.
+///
+/// Register fields are not checked against the register count here; [AVMCpu::execute](crate::hardware::cpu::AVMCpu::execute)
+/// and the loader's validation do that, see [Instruction::registers].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operands {
+    /// No operands, e.g. `$nop`, `$ret` or `$halt`.
+    None,
+    /// Up to three register fields in bits 40..48, 32..40 and 24..32. Fields the operation does not use are 0.
+    Registers { a: usize, b: usize, c: usize },
+    /// A register and a 40-bit immediate, as used by `$set` and `$lea`.
+    Immediate { reg: usize, value: u64 },
+    /// A 48-bit target address, as used by `$jmp`, `$call` and the flag branches.
+    Address(u64),
+    /// A 48-bit number, as used by `$syscall`.
+    Number(u64),
+    /// The exit code of `$exit`.
+    Code(u8),
+    /// A register, an access size in bits and a 32-bit absolute address.
+    Absolute { reg: usize, size: usize, address: u64 },
+    /// A register, an access size in bits, a base register and a 24-bit displacement.
+    Memory { reg: usize, size: usize, base: usize, disp: u64 },
+    /// A condition, two 4-bit registers and a 36-bit target address, as used by `$jmc`.
+    Branch { cond: u64, lhs: usize, rhs: usize, address: u64 },
+    /// A condition, a 4-bit register, an 8-bit immediate and a 32-bit target address, as used by `$jmci`.
+    BranchImmediate { cond: u64, reg: usize, imm: u64, address: u64 },
+    /// A condition, two 4-bit registers, a source and a destination register, as used by `$cmov`.
+    ConditionalMove { cond: u64, lhs: usize, rhs: usize, src: usize, dst: usize },
+    /// The address, expected value, new value and result registers of `$cas`.
+    CompareAndSwap { address: usize, expected: usize, new: usize, result: usize }
+}
+
+/// Splits an instruction into its operation and typed operands.
+///
+/// # Returns
+/// - `Ok(Instruction)` for a known operation.
+/// - `Err(Exception::UnexpectedOpcode)` otherwise.
+pub fn decode(instr: u64) -> Result<Instruction, Exception> {
+    let opcode = Opcode::try_from(instr >> 48)?;
+    let a = ((instr >> 40) & 0xFF) as usize;
+    let b = ((instr >> 32) & 0xFF) as usize;
+    let c = ((instr >> 24) & 0xFF) as usize;
+    let d = ((instr >> 16) & 0xFF) as usize;
+    let cond = (instr >> 44) & 0xF;
+    let operands = match opcode {
+        Opcode::Nop | Opcode::Ret | Opcode::Halt => Operands::None,
+        Opcode::Set | Opcode::Lea => Operands::Immediate { reg: a, value: instr & 0xFF_FFFF_FFFF },
+        Opcode::Jmp | Opcode::Call | Opcode::Jz | Opcode::Jnz | Opcode::Js | Opcode::Jns | Opcode::Jc | Opcode::Jnc
+        | Opcode::Jo | Opcode::Jno => Operands::Address(instr & 0xFFFF_FFFF_FFFF),
+        Opcode::Syscall => Operands::Number(instr & 0xFFFF_FFFF_FFFF),
+        Opcode::Exit => Operands::Code(a as u8),
+        Opcode::LoadAbsolute | Opcode::StoreAbsolute => Operands::Absolute { reg: a, size: b, address: instr & 0xFFFF_FFFF },
+        Opcode::Load | Opcode::Store | Opcode::Addm | Opcode::Subm => Operands::Memory { reg: a, size: b, base: c, disp: instr & 0xFF_FFFF },
+        Opcode::Jmc => Operands::Branch { cond, lhs: a & 0xF, rhs: b >> 4, address: instr & 0xF_FFFF_FFFF },
+        Opcode::Jmci => Operands::BranchImmediate { cond, reg: a & 0xF, imm: b as u64, address: instr & 0xFFFF_FFFF },
+        Opcode::Cmov => Operands::ConditionalMove { cond, lhs: a & 0xF, rhs: b >> 4, src: c, dst: d },
+        Opcode::Cas => Operands::CompareAndSwap { address: a, expected: b, new: c, result: d },
+        _ => Operands::Registers { a, b, c }
+    };
+    Ok(Instruction { opcode, operands })
+}
+
+/// Encodes an instruction without operands, e.g. `$nop`, `$ret` or `$halt`.
+pub fn encode_bare(opcode: Opcode) -> u64 {
+    encode::bare(opcode as u64)
+}
+/// Encodes an instruction with one register operand, e.g. `$push` or `$inc`.
+pub fn encode_unary(opcode: Opcode, a: usize) -> u64 {
+    encode::unary(opcode as u64, a)
+}
+/// Encodes an instruction with two register operands, e.g. `$move` or `$cmp`.
+pub fn encode_binary(opcode: Opcode, a: usize, b: usize) -> u64 {
+    encode::binary(opcode as u64, a, b)
+}
+/// Encodes an instruction with three register operands, e.g. `$add` or `$memcpy`.
+pub fn encode_ternary(opcode: Opcode, a: usize, b: usize, c: usize) -> u64 {
+    encode::ternary(opcode as u64, a, b, c)
+}
+/// Encodes `reg $set imm`, keeping the low 40 bits of `imm`.
+pub fn encode_set(reg: usize, imm: u64) -> u64 {
+    encode::set(reg, imm)
+}
+/// Encodes `reg $lea address`, keeping the low 40 bits of `address`.
+pub fn encode_lea(reg: usize, address: u64) -> u64 {
+    encode::lea(reg, address)
+}
+/// Encodes `r1 $add r2 -> dst`.
+pub fn encode_add(r1: usize, r2: usize, dst: usize) -> u64 {
+    encode_ternary(Opcode::Add, r1, r2, dst)
+}
+/// Encodes `r1 $sub r2 -> dst`.
+pub fn encode_sub(r1: usize, r2: usize, dst: usize) -> u64 {
+    encode_ternary(Opcode::Sub, r1, r2, dst)
+}
+/// Encodes `r1 $mul r2 -> dst`.
+pub fn encode_mul(r1: usize, r2: usize, dst: usize) -> u64 {
+    encode_ternary(Opcode::Mul, r1, r2, dst)
+}
+/// Encodes `from $move to`.
+pub fn encode_move(from: usize, to: usize) -> u64 {
+    encode_binary(Opcode::Move, from, to)
+}
+/// Encodes `[address] $move reg` or `reg $move [address]`, accessing `size` bits. The address keeps its low 32 bits.
+pub fn encode_absolute(opcode: Opcode, reg: usize, size: usize, address: u64) -> u64 {
+    encode::absolute(opcode as u64, reg, size, address)
+}
+/// Encodes `[base + disp] $move reg`, loading `size` bits. The displacement keeps its low 24 bits.
+pub fn encode_load(reg: usize, size: usize, base: usize, disp: u64) -> u64 {
+    encode::load(reg, size, base, disp)
+}
+/// Encodes `reg $move [base + disp]`, storing `size` bits. The displacement keeps its low 24 bits.
+pub fn encode_store(reg: usize, size: usize, base: usize, disp: u64) -> u64 {
+    encode::memory(encode::STORE, reg, size, base, disp)
+}
+/// Encodes `reg $addm [base + disp]` or `reg $subm [base + disp]`, updating `size` bits in memory.
+pub fn encode_update(opcode: Opcode, reg: usize, size: usize, base: usize, disp: u64) -> u64 {
+    encode::memory(opcode as u64, reg, size, base, disp)
+}
+/// Encodes `address $cas expected new -> result`.
+pub fn encode_cas(address: usize, expected: usize, new: usize, result: usize) -> u64 {
+    encode::quaternary(encode::CAS, address, expected, new, result)
+}
+/// Encodes a jump or call whose target address occupies bits 0..48, e.g. `$jmp`, `$call` or `$jz`, or a `$syscall`
+/// with its number there.
+pub fn encode_jump(opcode: Opcode, address: u64) -> u64 {
+    encode::jump(opcode as u64, address)
+}
+/// Encodes `r1 $jmc cond r2` with registers below 16 and a 36-bit target address.
+pub fn encode_jmc(cond: u64, r1: usize, r2: usize, address: u64) -> u64 {
+    encode::jmc(cond, r1, r2, address)
+}
+/// Encodes `reg $jmci cond imm` with a register below 16, an 8-bit immediate and a 32-bit target address.
+pub fn encode_jmci(cond: u64, reg: usize, imm: u64, address: u64) -> u64 {
+    encode::jmci(cond, reg, imm, address)
+}
+/// Encodes `lhs $cmov cond rhs src -> dst` with compared registers below 16.
+pub fn encode_cmov(cond: u64, lhs: usize, rhs: usize, src: usize, dst: usize) -> u64 {
+    encode::cmov(cond, lhs, rhs, src, dst)
+}
+/// Encodes `$exit code`.
+pub fn encode_exit(code: u8) -> u64 {
+    encode_unary(Opcode::Exit, code as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_instructions_decode_to_their_operands() {
+        assert_eq!(decode(encode_add(1, 2, 3)), Ok(Instruction { opcode: Opcode::Add, operands: Operands::Registers { a: 1, b: 2, c: 3 } }));
+        assert_eq!(encode_add(1, 2, 3), 0xCD02_0102_0300_0000);
+        assert_eq!(encode_set(4, 0x2A), 0xCD01_0400_0000_002A);
+        assert_eq!(encode_store(2, 64, 1, 8), 0xCD09_0240_0100_0008);
+        assert_eq!(encode_jmc(0xF, 1, 2, 0x1000_0040), 0xCF01_F120_1000_0040);
+        assert_eq!(decode(encode_exit(3)), Ok(Instruction { opcode: Opcode::Exit, operands: Operands::Code(3) }));
+        assert_eq!(decode(0x1234_0000_0000_0000), Err(Exception::UnexpectedOpcode(0x1234)));
+    }
+
+    #[test]
+    fn every_operand_layout_decodes_its_fields() {
+        let operands = |instr: u64| decode(instr).unwrap().operands;
+        assert_eq!(operands(encode_bare(Opcode::Ret)), Operands::None);
+        assert_eq!(operands(encode_unary(Opcode::Push, 7)), Operands::Registers { a: 7, b: 0, c: 0 });
+        assert_eq!(operands(encode_set(4, 0x12_3456_789A)), Operands::Immediate { reg: 4, value: 0x12_3456_789A });
+        assert_eq!(operands(encode_jump(Opcode::Jz, 0x1000_0040)), Operands::Address(0x1000_0040));
+        assert_eq!(operands(encode_jump(Opcode::Syscall, 2)), Operands::Number(2));
+        assert_eq!(operands(encode_absolute(Opcode::LoadAbsolute, 3, 64, 0x1000_0010)), Operands::Absolute { reg: 3, size: 64, address: 0x1000_0010 });
+        assert_eq!(operands(encode_load(2, 32, 1, 0x10)), Operands::Memory { reg: 2, size: 32, base: 1, disp: 0x10 });
+        assert_eq!(operands(encode_jmc(0xB, 1, 2, 0x1000_0040)), Operands::Branch { cond: 0xB, lhs: 1, rhs: 2, address: 0x1000_0040 });
+        assert_eq!(operands(encode_jmci(0xC, 4, 5, 0x1000_0040)), Operands::BranchImmediate { cond: 0xC, reg: 4, imm: 5, address: 0x1000_0040 });
+        assert_eq!(operands(encode_cmov(0xA, 1, 2, 3, 4)), Operands::ConditionalMove { cond: 0xA, lhs: 1, rhs: 2, src: 3, dst: 4 });
+        assert_eq!(operands(encode_cas(1, 2, 3, 4)), Operands::CompareAndSwap { address: 1, expected: 2, new: 3, result: 4 });
+    }
+
+    #[test]
+    fn only_used_register_fields_are_reported() {
+        assert_eq!(decode(encode_ternary(Opcode::Move, 1, 2, 0xFF)).unwrap().registers(), vec![1, 2]);
+        assert_eq!(decode(encode_load(2, 32, 1, 0x10)).unwrap().registers(), vec![2, 1]);
+        assert!(decode(encode_jump(Opcode::Jmp, 0x1000_0000)).unwrap().registers().is_empty());
+    }
+}
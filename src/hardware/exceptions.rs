@@ -4,19 +4,20 @@
 //! for issues encountered during execution. Exceptions are represented as variants of the [Exception] enum,
 //! each tailored to a specific type of error, such as invalid memory access, illegal arguments, or unexpected
 //! opcodes. These exceptions implement the **`Debug`** and **`Display`** traits to enable clear error descriptions
-//! and easy debugging.
+//! and easy debugging, as well as [std::error::Error] so they can be boxed or propagated with `?`.
 //!
+use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 
-use crate::hardware::memory::{MEMORY_START_ADDRESS, MEMORY_SIZE};
-
 /// Enum representing various exceptions that can occur in the AetherVM.
 ///
 /// The [Exception] enum provides specific error cases for common problems encountered during execution, such as:
 /// - Memory access violations.
 /// - Illegal size arguments.
 /// - Unexpected conditions or opcodes.
-#[derive(Debug)]
+///
+/// [Exception::Halt] is not an error: it is how `$halt` and `$exit` hand control back to the caller.
+#[derive(Debug, PartialEq)]
 pub enum Exception {
     /// Raised when an address is accessed that falls outside the defined memory bounds.
     AddressNotInMemoryBounds(u64),
@@ -27,19 +28,88 @@ pub enum Exception {
     /// Raised when an unexpected condition is encountered during conditional execution.
     UnexpectedCondition(u64),
     /// Raised when an unrecognized opcode is encountered during instruction decoding.
-    UnexpectedOpcode(u64)
+    UnexpectedOpcode(u64),
+    /// Raised when a device is mapped onto an empty range or a range already claimed by another device.
+    DeviceRangeConflict(u64, u64),
+    /// Raised when the program stops via `$halt` or `$exit`/`$quit`, carrying the exit code.
+    Halt(i32),
+    /// Raised when serialized snapshot data has a wrong magic or is truncated.
+    MalformedSnapshot,
+    /// Raised when a register index outside of `0..32` is used.
+    IllegalRegisterIndex(usize),
+    /// Raised when a push would move the stack pointer below the stack limit.
+    StackOverflow(u64),
+    /// Raised when a pop would move the stack pointer above the stack base.
+    StackUnderflow(u64),
+    /// Raised when a memory access is not aligned to its size.
+    UnalignedAccess(u64),
+    /// Raised when a bytecode image does not start with the expected magic and version.
+    InvalidProgramHeader,
+    /// Raised when the instructions of a bytecode image fail validation, carrying every problem found.
+    InvalidProgram(Vec<Exception>),
+    /// Raised when a bytecode image holds more bytes than fit into memory.
+    ProgramTooLarge(u64),
+    /// Raised when a bytecode file cannot be read, carrying the reason.
+    ProgramNotReadable(String),
+    /// Raised when a run executes more instructions than its budget allows, carrying the budget.
+    InstructionBudgetExceeded(u64),
+    /// Raised when an integer division or remainder has a zero divisor.
+    DivisionByZero,
+    /// Raised when a write lands in a read-only range, such as the code of a loaded program.
+    WriteToReadOnlyMemory(u64),
+    /// Raised when `$syscall` is executed with a number that has no registered handler.
+    UnknownSyscall(u64),
+    /// Wraps the exception an instruction raised while stepping, together with the address of that instruction.
+    Fault { ip: u64, cause: Box<Exception> }
 }
 impl Display for Exception {
     /// Provides a human-readable description of each exception.
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Exception::AddressNotInMemoryBounds(addr) => write!(f, "Address {} is not in memory bounds ({}..{})", addr, MEMORY_START_ADDRESS, (MEMORY_START_ADDRESS + MEMORY_SIZE)),
+            Exception::AddressNotInMemoryBounds(addr) => write!(f, "Address {} is not in memory bounds", addr),
             Exception::IllegalSizeArgument(size) => write!(f, "Size {} is not allowed. Size can be one of that: 8, 16, 32, 64", size),
             Exception::InstructionAccessFaultOnAddress(addr) => write!(f, "No instructions on address {} or address out of bounds", addr),
             Exception::UnexpectedCondition(cond) => write!(f, "Unexpected condition {:X}", cond),
-            Exception::UnexpectedOpcode(opcode) => write!(f, "Unexpected opcode {:X}", opcode)
+            Exception::UnexpectedOpcode(opcode) => write!(f, "Unexpected opcode {:X}", opcode),
+            Exception::DeviceRangeConflict(start, end) => write!(f, "Device range {:X}..{:X} is empty or overlaps an already mapped device", start, end),
+            Exception::Halt(code) => write!(f, "Program halted with exit code {}", code),
+            Exception::MalformedSnapshot => write!(f, "Snapshot data is malformed or truncated"),
+            Exception::IllegalRegisterIndex(index) => write!(f, "Register index {} is out of range. Registers are numbered 0..32", index),
+            Exception::StackOverflow(sp) => write!(f, "Stack overflow with stack pointer {:X}", sp),
+            Exception::StackUnderflow(sp) => write!(f, "Stack underflow with stack pointer {:X}", sp),
+            Exception::UnalignedAccess(addr) => write!(f, "Unaligned memory access on address {:X}", addr),
+            Exception::InvalidProgramHeader => write!(f, "Program does not start with a valid AVM header"),
+            Exception::InvalidProgram(problems) => {
+                let problems: Vec<String> = problems.iter().map(Exception::to_string).collect();
+                write!(f, "Program failed validation: {}", problems.join("; "))
+            }
+            Exception::ProgramTooLarge(len) => write!(f, "Program of {} bytes does not fit into memory", len),
+            Exception::ProgramNotReadable(reason) => write!(f, "Program could not be read: {}", reason),
+            Exception::InstructionBudgetExceeded(max) => write!(f, "Program exceeded its budget of {} instructions", max),
+            Exception::DivisionByZero => write!(f, "Integer division by zero"),
+            Exception::WriteToReadOnlyMemory(addr) => write!(f, "Write to read-only memory on address {:X}", addr),
+            Exception::UnknownSyscall(number) => write!(f, "No handler registered for syscall {}", number),
+            Exception::Fault { ip, cause } => write!(f, "{} (at instruction {:X})", cause, ip)
+        }
+    }
+}
+impl Error for Exception {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Exception::Fault { cause, .. } => Some(cause.as_ref()),
+            _ => None
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    #[test]
+    fn exception_boxes_as_error() {
+        let error: Box<dyn Error> = Exception::IllegalSizeArgument(12).into();
+        assert_eq!(error.to_string(), "Size 12 is not allowed. Size can be one of that: 8, 16, 32, 64");
+        assert_eq!(Exception::AddressNotInMemoryBounds(0x100).to_string(), "Address 256 is not in memory bounds");
+    }
+}
@@ -0,0 +1,216 @@
+//! # AetherVM Program Loader Module
+//!
+//! This module reads bytecode images and copies them into the memory of an [AVMCpu], where execution starts at
+//! [MEMORY_START_ADDRESS](crate::hardware::memory::MEMORY_START_ADDRESS). An image is laid out as follows:
+//! - The magic bytes `AVM\0`.
+//! - A single version byte, currently [PROGRAM_VERSION].
+//! - The length of the code in bytes, as a little-endian `u64`.
+//! - The instruction bytes, followed by the data section. Both are copied verbatim into memory, the data directly
+//!   after the code.
+//!
+//! Loading runs [validate_program] over the instruction bytes, so malformed code is rejected before it runs rather
+//! than deep into execution. The data section is not validated, so it may hold arbitrary bytes.
+//!
+use std::fs;
+use std::path::Path;
+
+/// The magic bytes and image format version, shared with the writer in [bctranslator::write_bytecode].
+pub use bctranslator::{PROGRAM_MAGIC, PROGRAM_VERSION};
+
+use crate::hardware::cpu::{AVMCpu, INSTRUCTION_SIZE};
+use crate::hardware::encode::{decode, Operands};
+use crate::hardware::exceptions::Exception;
+use crate::hardware::memory::MEMORY_START_ADDRESS;
+
+/// The length of the image header in bytes.
+pub const PROGRAM_HEADER_SIZE: usize = PROGRAM_MAGIC.len() + 1 + 8;
+
+/// Reads a bytecode image from disk and loads it into the CPU's memory.
+///
+/// # Returns
+/// - `Ok(())` if the program was loaded.
+/// - `Err(Exception::ProgramNotReadable)` if the file cannot be read.
+/// - Any error of [load_program_bytes] otherwise.
+pub fn load_program(path: &Path, cpu: &mut AVMCpu) -> Result<(), Exception> {
+    let bytes = fs::read(path).map_err(|err| Exception::ProgramNotReadable(format!("{}: {}", path.display(), err)))?;
+    load_program_bytes(&bytes, cpu)
+}
+
+/// Validates a bytecode image and its instructions (see [validate_program]) and copies them to [MEMORY_START_ADDRESS].
+///
+/// The loaded instructions are marked read-only, so a program cannot overwrite its own code, and become the CPU's
+/// code range (see [AVMCpu::set_code_range]), so it cannot execute past its end either. The data section is loaded
+/// directly after the code and stays writable.
+///
+/// # Returns
+/// - `Ok(())` if the program was loaded.
+/// - `Err(Exception::InvalidProgramHeader)` if the magic or version is wrong.
+/// - `Err(Exception::InvalidProgram)` with every problem of the instructions.
+/// - `Err(Exception::ProgramTooLarge)` if the code and data do not fit into memory.
+pub fn load_program_bytes(bytes: &[u8], cpu: &mut AVMCpu) -> Result<(), Exception> {
+    let (code, data) = program_sections(bytes)?;
+    validate_program(code).map_err(Exception::InvalidProgram)?;
+    let image = [code, data].concat();
+    if image.len() as u64 > cpu.bus_mut().memory().size() {
+        return Err(Exception::ProgramTooLarge(image.len() as u64))
+    }
+    cpu.bus_mut().memory_mut().write_pages(&[(0, image)])?;
+    let range = MEMORY_START_ADDRESS..MEMORY_START_ADDRESS + code.len() as u64;
+    cpu.bus_mut().protect(range.clone());
+    cpu.set_code_range(Some(range));
+    Ok(())
+}
+
+/// Checks the image header and splits the bytes that follow it into the code and the data section.
+///
+/// # Returns
+/// - `Ok((&[u8], &[u8]))` with the instruction bytes and the data bytes.
+/// - `Err(Exception::InvalidProgramHeader)` if the magic or version is wrong or the code length exceeds the image.
+pub fn program_sections(bytes: &[u8]) -> Result<(&[u8], &[u8]), Exception> {
+    let Some((header, rest)) = bytes.split_at_checked(PROGRAM_HEADER_SIZE) else {
+        return Err(Exception::InvalidProgramHeader)
+    };
+    if &header[..4] != PROGRAM_MAGIC || header[4] != PROGRAM_VERSION {
+        return Err(Exception::InvalidProgramHeader)
+    }
+    let code_len = u64::from_le_bytes(header[5..].try_into().unwrap());
+    usize::try_from(code_len).ok()
+        .and_then(|code_len| rest.split_at_checked(code_len))
+        .ok_or(Exception::InvalidProgramHeader)
+}
+
+/// Scans the instruction bytes of a program, as returned by [program_sections], for words the CPU would reject.
+///
+/// Every little-endian word is checked for an unknown opcode, register fields outside of `0..32` and memory accesses
+/// with a size other than 8, 16, 32 or 64 bits. Trailing bytes that do not form a whole word are ignored.
+///
+/// # Returns
+/// - `Ok(())` if every word is a valid instruction.
+/// - `Err(Vec<Exception>)` with one [Exception::Fault] per problem, carrying the address the word would be loaded to.
+pub fn validate_program(bytes: &[u8]) -> Result<(), Vec<Exception>> {
+    let mut problems = Vec::new();
+    for (i, chunk) in bytes.chunks_exact(INSTRUCTION_SIZE as usize).enumerate() {
+        let instr = u64::from_le_bytes(chunk.try_into().unwrap());
+        let ip = MEMORY_START_ADDRESS + i as u64 * INSTRUCTION_SIZE;
+        problems.extend(validate_instr(instr).into_iter().map(|cause| Exception::Fault { ip, cause: Box::new(cause) }));
+    }
+    if problems.is_empty() { Ok(()) } else { Err(problems) }
+}
+
+/// Returns every problem of a single instruction.
+fn validate_instr(instr: u64) -> Vec<Exception> {
+    let instruction = match decode(instr) {
+        Ok(instruction) => instruction,
+        Err(e) => return vec![e]
+    };
+    let size = match instruction.operands {
+        Operands::Memory { size, .. } | Operands::Absolute { size, .. } => Some(size),
+        _ => None
+    };
+    let mut problems: Vec<Exception> = instruction.registers().into_iter()
+        .filter(|&reg| reg >= 32)
+        .map(Exception::IllegalRegisterIndex)
+        .collect();
+    if let Some(size) = size.filter(|size| !matches!(size, 8 | 16 | 32 | 64)) {
+        problems.push(Exception::IllegalSizeArgument(size));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::encode::{encode_bare, encode_jump, encode_set, encode_store};
+    use crate::hardware::memory::AVMDevice;
+    use crate::hardware::opcode::Opcode;
+
+    fn image(code: &[u64], data: &[u8]) -> Vec<u8> {
+        let mut image = PROGRAM_MAGIC.to_vec();
+        image.push(PROGRAM_VERSION);
+        image.extend_from_slice(&(code.len() as u64 * 8).to_le_bytes());
+        image.extend(code.iter().flat_map(|instr| instr.to_le_bytes()));
+        image.extend_from_slice(data);
+        image
+    }
+
+    #[test]
+    fn loads_code_after_a_valid_header() {
+        let image = image(&[encode_set(1, 0x2A)], &[]);
+        let mut cpu = AVMCpu::new();
+        load_program_bytes(&image, &mut cpu).unwrap();
+        assert_eq!(cpu.bus_mut().read(MEMORY_START_ADDRESS, 64), Ok(encode_set(1, 0x2A)));
+
+        assert_eq!(load_program_bytes(b"AVX\0\x02\0\0\0\0\0\0\0\0", &mut cpu), Err(Exception::InvalidProgramHeader));
+        assert_eq!(load_program_bytes(b"AVM\0\x01\0\0\0\0\0\0\0\0", &mut cpu), Err(Exception::InvalidProgramHeader));
+        assert_eq!(load_program_bytes(b"AVM\0\x02\x10\0\0\0\0\0\0\0", &mut cpu), Err(Exception::InvalidProgramHeader));
+        assert_eq!(load_program_bytes(b"AVM", &mut cpu), Err(Exception::InvalidProgramHeader));
+    }
+
+    #[test]
+    fn running_past_the_program_faults() {
+        let image = image(&[encode_jump(Opcode::Jmp, 0x1000_0010), encode_bare(Opcode::Halt)], &[]);
+        let mut cpu = AVMCpu::new();
+        load_program_bytes(&image, &mut cpu).unwrap();
+        assert_eq!(cpu.run(), Err(Exception::InstructionAccessFaultOnAddress(MEMORY_START_ADDRESS + 16)));
+    }
+
+    #[test]
+    fn validation_reports_every_bad_word() {
+        let words = [
+            encode_set(1, 0x2A),
+            0x1234_0000_0000_0000,
+            encode_store(2, 12, 40, 0),
+            encode_bare(Opcode::Halt),
+        ];
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        assert_eq!(validate_program(&bytes), Err(vec![
+            Exception::Fault { ip: MEMORY_START_ADDRESS + 8, cause: Box::new(Exception::UnexpectedOpcode(0x1234)) },
+            Exception::Fault { ip: MEMORY_START_ADDRESS + 16, cause: Box::new(Exception::IllegalRegisterIndex(40)) },
+            Exception::Fault { ip: MEMORY_START_ADDRESS + 16, cause: Box::new(Exception::IllegalSizeArgument(12)) },
+        ]));
+        assert_eq!(validate_program(&bytes[..8]), Ok(()));
+    }
+
+    #[test]
+    fn loading_rejects_invalid_instructions() {
+        let image = image(&[encode_set(1, 0x2A), 0x1234_0000_0000_0000], &[]); // unknown opcode
+        let mut cpu = AVMCpu::new();
+        let error = load_program_bytes(&image, &mut cpu).unwrap_err();
+        assert_eq!(error, Exception::InvalidProgram(vec![
+            Exception::Fault { ip: MEMORY_START_ADDRESS + 8, cause: Box::new(Exception::UnexpectedOpcode(0x1234)) }
+        ]));
+        assert_eq!(error.to_string(), "Program failed validation: Unexpected opcode 1234 (at instruction 10000008)");
+        assert_eq!(cpu.bus_mut().read(MEMORY_START_ADDRESS, 64), Ok(0));
+    }
+
+    #[test]
+    fn data_is_not_validated() {
+        let image = image(&[encode_set(1, 0x2A)], &0x1234_0000_0000_0000u64.to_le_bytes());
+        let mut cpu = AVMCpu::new();
+        assert_eq!(load_program_bytes(&image, &mut cpu), Ok(()));
+        assert_eq!(cpu.bus_mut().read(MEMORY_START_ADDRESS + 8, 64), Ok(0x1234_0000_0000_0000));
+    }
+
+    #[test]
+    fn loaded_code_is_read_only() {
+        let image = image(&[encode_set(1, 0x1000_0000), encode_store(2, 64, 1, 8)], &[]);
+        let mut cpu = AVMCpu::new();
+        load_program_bytes(&image, &mut cpu).unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.step(), Err(Exception::Fault {
+            ip: MEMORY_START_ADDRESS + 8,
+            cause: Box::new(Exception::WriteToReadOnlyMemory(MEMORY_START_ADDRESS + 8))
+        }));
+        assert!(cpu.bus_mut().write(MEMORY_START_ADDRESS + 16, 1, 64).is_ok());
+    }
+
+    #[test]
+    fn data_is_loaded_after_the_code_and_stays_writable() {
+        let image = image(&[encode_set(1, 0x2A)], b"Hello, world!\0");
+        let mut cpu = AVMCpu::new();
+        load_program_bytes(&image, &mut cpu).unwrap();
+        assert_eq!(cpu.bus_mut().read(MEMORY_START_ADDRESS + 8, 64), Ok(u64::from_le_bytes(*b"Hello, w")));
+        assert!(cpu.bus_mut().write(MEMORY_START_ADDRESS + 8, 0, 64).is_ok());
+        assert_eq!(cpu.run(), Err(Exception::InstructionAccessFaultOnAddress(MEMORY_START_ADDRESS + 8)));
+    }
+}
@@ -2,15 +2,22 @@
 //!
 //! This module implements the memory management system for the AetherVM. It provides an abstraction layer for memory access
 //! and manipulation via the [AVMBus] and [AVMMemory] structures. The memory is organized with a starting address and a fixed
-//! size, and it supports both byte-level and word-level read/write operations. The module ensures safety by validating memory
-//! bounds and size arguments before performing operations.
+//! size, and it supports both byte-level and word-level read/write operations in a configurable [Endianness]. The module
+//! ensures safety by validating memory bounds and size arguments before performing operations.
 //!
+//! Memory is either dense, backed by one allocation spanning the whole size, or sparse, backed by pages allocated on
+//! first write (see [AVMMemory::sparse]). Both behave identically to the CPU.
+//!
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
 use crate::hardware::exceptions::Exception;
 
 /// The starting address of the AetherVM memory space.
 pub const MEMORY_START_ADDRESS: u64 = 0x10000000;
 /// The size of the AetherVM memory space.
 pub const MEMORY_SIZE: u64 = 0x40000000;
+/// The granularity, in bytes, at which written memory is tracked.
+pub const PAGE_SIZE: usize = 0x1000;
 
 /// Trait for AetherVM devices to standardize memory read/write operations.
 pub trait AVMDevice {
@@ -38,8 +45,17 @@ pub trait AVMDevice {
 }
 
 /// Represents the memory bus, which interfaces between the CPU and memory devices.
+///
+/// Ranges of RAM can be marked read-only with [AVMBus::protect], after which writes into them fault.
 pub struct AVMBus {
-    memory: AVMMemory
+    memory: AVMMemory,
+    devices: Vec<(Range<u64>, Box<dyn AVMDevice>)>,
+    read_only: Vec<Range<u64>>
+}
+impl Default for AVMBus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl AVMBus {
     /// Creates a new instance of the memory bus.
@@ -47,109 +63,369 @@ impl AVMBus {
     /// # Returns
     /// A new [AVMBus] instance with initialized memory.
     pub fn new() -> AVMBus {
+        Self::with_memory_size(MEMORY_SIZE)
+    }
+    /// Creates a memory bus like [AVMBus::new], with `size` bytes of memory instead of [MEMORY_SIZE].
+    pub fn with_memory_size(size: u64) -> AVMBus {
+        Self::with_memory(AVMMemory::with_size(size))
+    }
+    /// Creates a memory bus around already constructed memory, e.g. [AVMMemory::sparse].
+    pub fn with_memory(memory: AVMMemory) -> AVMBus {
         Self {
-            memory: AVMMemory::new()
+            memory,
+            devices: Vec::new(),
+            read_only: Vec::new()
+        }
+    }
+    /// Marks an address range as read-only, so that writes into it return [Exception::WriteToReadOnlyMemory].
+    ///
+    /// Loading memory directly, e.g. with [AVMMemory::write_pages], is not affected.
+    pub fn protect(&mut self, range: Range<u64>) {
+        if !range.is_empty() {
+            self.read_only.push(range);
         }
     }
+    /// Removes every read-only range added with [AVMBus::protect].
+    pub fn clear_protection(&mut self) {
+        self.read_only.clear();
+    }
+    /// Checks whether any byte of an access of `size` bits starting at `addr` is read-only.
+    fn is_read_only(&self, addr: u64, size: usize) -> bool {
+        let end = addr.saturating_add(size as u64 / 8);
+        self.read_only.iter().any(|range| addr < range.end && range.start < end)
+    }
+    /// Maps a device onto the bus at the given address range.
+    ///
+    /// Reads and writes that land inside the range are routed to the device instead of RAM.
+    /// The device receives the address as an offset from the start of its range.
+    ///
+    /// # Parameters
+    /// - `range`: The half-open address range claimed by the device.
+    /// - `device`: The device that owns the range.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the device was mapped.
+    /// - `Err(Exception)` if the range is empty or overlaps an already mapped device.
+    pub fn map_device(&mut self, range: Range<u64>, device: Box<dyn AVMDevice>) -> Result<(), Exception> {
+        if range.is_empty() || self.map_overlap(&range).is_some() {
+            return Err(Exception::DeviceRangeConflict(range.start, range.end))
+        }
+        self.devices.push((range, device));
+        Ok(())
+    }
+    /// Returns the RAM attached to the bus.
+    pub fn memory(&self) -> &AVMMemory {
+        &self.memory
+    }
+    /// Returns the RAM attached to the bus for modification.
+    pub fn memory_mut(&mut self) -> &mut AVMMemory {
+        &mut self.memory
+    }
+    /// Reads up to `len` bytes starting at `start`, going through mapped devices like the CPU does.
+    ///
+    /// The dump stops early at the first byte that cannot be read, so it is empty if `start` is not readable.
+    pub fn dump(&mut self, start: u64, len: usize) -> Vec<u8> {
+        (0..len as u64)
+            .map_while(|offset| start.checked_add(offset).and_then(|addr| self.read(addr, 8).ok()))
+            .map(|byte| byte as u8)
+            .collect()
+    }
+    /// Finds the device that owns `addr`, if any.
+    fn device_at(&mut self, addr: u64) -> Option<(u64, &mut Box<dyn AVMDevice>)> {
+        self.devices.iter_mut()
+            .find(|(range, _)| range.contains(&addr))
+            .map(|(range, device)| (addr - range.start, device))
+    }
+    /// Checks that an access of `size` bits starting at `addr` lies entirely within memory.
+    fn in_bounds(&self, addr: u64, size: usize) -> bool {
+        match addr.checked_add(size as u64 / 8) {
+            Some(end) => addr >= MEMORY_START_ADDRESS && end <= MEMORY_START_ADDRESS + self.memory.size(),
+            None => false
+        }
+    }
+    /// Finds an already mapped device range that overlaps the given one.
+    ///
+    /// # Returns
+    /// - `Some(Range<u64>)` with the conflicting range of the mapped device.
+    /// - `None` if the range is free.
+    pub fn map_overlap(&self, range: &Range<u64>) -> Option<Range<u64>> {
+        self.devices.iter()
+            .map(|(mapped, _)| mapped)
+            .find(|mapped| range.start < mapped.end && mapped.start < range.end)
+            .cloned()
+    }
 }
 impl AVMDevice for AVMBus {
     fn write(&mut self, addr: u64, data: u64, size: usize) -> Result<u64, Exception> {
-        if addr < MEMORY_START_ADDRESS || addr > (MEMORY_START_ADDRESS + MEMORY_SIZE) {
+        if let Some((offset, device)) = self.device_at(addr) {
+            return device.write(offset, data, size)
+        }
+        if !self.in_bounds(addr, size) {
             return Err(Exception::AddressNotInMemoryBounds(addr))
         }
+        if self.is_read_only(addr, size) {
+            return Err(Exception::WriteToReadOnlyMemory(addr))
+        }
         self.memory.write(addr, data, size)
     }
     fn read(&mut self, addr: u64, size: usize) -> Result<u64, Exception> {
-        if addr < MEMORY_START_ADDRESS || addr > (MEMORY_START_ADDRESS + MEMORY_SIZE) {
+        if let Some((offset, device)) = self.device_at(addr) {
+            return device.read(offset, size)
+        }
+        if !self.in_bounds(addr, size) {
             return Err(Exception::AddressNotInMemoryBounds(addr))
         }
         self.memory.read(addr, size)
     }
 }
 
+/// Renders bytes read from `start`, e.g. by [AVMBus::dump], as a classic hex dump.
+///
+/// Every line shows the address of its first byte, up to 16 bytes in hexadecimal and the same bytes as ASCII, with
+/// unprintable bytes shown as `.`:
+/// ```text
+/// 10000000:  48 65 6C 6C 6F 00 00 00  00 00 00 00 00 00 00 00  |Hello...........|
+/// ```
+pub fn hex_dump(start: u64, bytes: &[u8]) -> String {
+    bytes.chunks(16).enumerate().map(|(i, line)| {
+        let hex: Vec<String> = line.iter().map(|byte| format!("{:02X}", byte)).collect();
+        let (left, right) = hex.split_at(hex.len().min(8));
+        let ascii: String = line.iter().map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }).collect();
+        format!("{:08X}:  {:<23}  {:<23}  |{}|\n", start + i as u64 * 16, left.join(" "), right.join(" "), ascii)
+    }).collect()
+}
+
+/// The byte order in which multi-byte values are laid out in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endianness {
+    /// Least significant byte first.
+    #[default]
+    Little,
+    /// Most significant byte first.
+    Big
+}
+
+/// The storage behind [AVMMemory].
+enum Storage {
+    /// One zero-initialized allocation spanning all of memory.
+    Dense(Vec<u8>),
+    /// Pages keyed by page number, allocated on first write. Missing pages read as zero.
+    Sparse { pages: HashMap<u64, [u8; PAGE_SIZE]>, size: u64 }
+}
+
 /// Represents the physical memory of the AetherVM.
 pub struct AVMMemory {
-    data: Vec<u8>
+    storage: Storage,
+    written: BTreeSet<usize>,
+    endianness: Endianness
+}
+impl Default for AVMMemory {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl AVMMemory {
-    /// Creates a new instance of memory with a predefined capacity.
+    /// Creates a new instance of zero-initialized memory spanning [MEMORY_SIZE] bytes.
     ///
     /// # Returns
     /// A new [AVMMemory] instance.
     pub fn new() -> AVMMemory {
+        Self::with_size(MEMORY_SIZE)
+    }
+    /// Creates zero-initialized memory spanning `size` bytes, still starting at [MEMORY_START_ADDRESS].
+    pub fn with_size(size: u64) -> AVMMemory {
         Self {
-            data: Vec::with_capacity(1073741824)
+            storage: Storage::Dense(vec![0; size as usize]),
+            written: BTreeSet::new(),
+            endianness: Endianness::default()
+        }
+    }
+    /// Creates memory spanning `size` bytes that only allocates a page when it is first written.
+    ///
+    /// Reads from pages that were never written return zero, so this behaves like [AVMMemory::with_size]
+    /// without reserving the whole size up front.
+    pub fn sparse(size: u64) -> AVMMemory {
+        Self {
+            storage: Storage::Sparse { pages: HashMap::new(), size },
+            written: BTreeSet::new(),
+            endianness: Endianness::default()
+        }
+    }
+    /// Zeroes memory in place, keeping its backend, size and byte order.
+    ///
+    /// Dense memory keeps its allocation and only zeroes the pages that were written, sparse memory drops its pages.
+    pub fn clear(&mut self) {
+        match &mut self.storage {
+            Storage::Dense(data) => {
+                for &page in &self.written {
+                    let start = page * PAGE_SIZE;
+                    let end = (start + PAGE_SIZE).min(data.len());
+                    data[start..end].fill(0);
+                }
+            }
+            Storage::Sparse { pages, .. } => pages.clear()
+        }
+        self.written.clear();
+    }
+    /// Returns the number of bytes of memory.
+    pub fn size(&self) -> u64 {
+        match &self.storage {
+            Storage::Dense(data) => data.len() as u64,
+            Storage::Sparse { size, .. } => *size
+        }
+    }
+    /// Returns the number of pages currently allocated by sparse memory, or `None` for dense memory.
+    pub fn allocated_pages(&self) -> Option<usize> {
+        match &self.storage {
+            Storage::Dense(_) => None,
+            Storage::Sparse { pages, .. } => Some(pages.len())
+        }
+    }
+    /// Returns the byte at `index` of the backing storage.
+    fn byte(&self, index: usize) -> u8 {
+        match &self.storage {
+            Storage::Dense(data) => data[index],
+            Storage::Sparse { pages, .. } => pages.get(&((index / PAGE_SIZE) as u64)).map_or(0, |page| page[index % PAGE_SIZE])
+        }
+    }
+    /// Sets the byte at `index` of the backing storage, allocating its page if needed.
+    fn set_byte(&mut self, index: usize, value: u8) {
+        match &mut self.storage {
+            Storage::Dense(data) => data[index] = value,
+            Storage::Sparse { pages, .. } => {
+                pages.entry((index / PAGE_SIZE) as u64).or_insert([0; PAGE_SIZE])[index % PAGE_SIZE] = value;
+            }
+        }
+    }
+    /// Returns the byte order used for multi-byte accesses.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+    /// Sets the byte order used for multi-byte accesses. Memory is little-endian by default.
+    pub fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+    }
+    /// Returns a copy of every page that has been written to.
+    ///
+    /// Pages that were never written are still zero and are left out.
+    ///
+    /// # Returns
+    /// A list of `(offset, bytes)` pairs, where `offset` is relative to [MEMORY_START_ADDRESS].
+    pub fn written_pages(&self) -> Vec<(u64, Vec<u8>)> {
+        self.written.iter()
+            .map(|&page| {
+                let start = page * PAGE_SIZE;
+                let end = (start + PAGE_SIZE).min(self.size() as usize);
+                (start as u64, (start..end).map(|index| self.byte(index)).collect())
+            })
+            .collect()
+    }
+    /// Copies pages produced by [AVMMemory::written_pages] back into memory.
+    ///
+    /// # Returns
+    /// - `Ok(())` if every page was written.
+    /// - `Err(Exception)` if a page does not fit into memory.
+    pub fn write_pages(&mut self, pages: &[(u64, Vec<u8>)]) -> Result<(), Exception> {
+        for (offset, bytes) in pages {
+            let start = *offset as usize;
+            if start + bytes.len() > self.size() as usize {
+                return Err(Exception::AddressNotInMemoryBounds(MEMORY_START_ADDRESS + offset))
+            }
+            for (i, &byte) in bytes.iter().enumerate() {
+                self.set_byte(start + i, byte);
+            }
+            if !bytes.is_empty() {
+                self.mark_written(start, bytes.len());
+            }
+        }
+        Ok(())
+    }
+    /// Validates an access of `size` bits at `addr` before the backing storage is indexed.
+    ///
+    /// Accesses must be naturally aligned to their size and end within memory.
+    ///
+    /// # Returns
+    /// - `Ok(usize)` with the index of the first byte in the backing storage.
+    /// - `Err(Exception::IllegalSizeArgument)` if `size` is not 8, 16, 32 or 64.
+    /// - `Err(Exception::UnalignedAccess)` if `addr` is not a multiple of the access width.
+    /// - `Err(Exception::AddressNotInMemoryBounds)` if the access does not fit into memory.
+    fn check_access(&self, addr: u64, size: usize) -> Result<usize, Exception> {
+        if !matches!(size, 8 | 16 | 32 | 64) {
+            return Err(Exception::IllegalSizeArgument(size))
+        }
+        let bytes = size / 8;
+        if !addr.is_multiple_of(bytes as u64) {
+            return Err(Exception::UnalignedAccess(addr))
+        }
+        match addr.checked_sub(MEMORY_START_ADDRESS) {
+            Some(index) if index + bytes as u64 <= self.size() => Ok(index as usize),
+            _ => Err(Exception::AddressNotInMemoryBounds(addr))
+        }
+    }
+    fn mark_written(&mut self, index: usize, len: usize) {
+        for page in (index / PAGE_SIZE)..=((index + len - 1) / PAGE_SIZE) {
+            self.written.insert(page);
         }
     }
     /// Reads a single byte from memory.
     fn load_byte(&self, addr: u64) -> u64 {
-        let index = (addr - MEMORY_START_ADDRESS) as usize;
-        self.data[index] as u64
+        self.load_bytes(addr, 1)
     }
     /// Reads a 16-bit word from memory.
     fn load_short(&self, addr: u64) -> u64 {
-        let index = (addr - MEMORY_START_ADDRESS) as usize;
-        (self.data[index] as u64) | ((self.data[index + 1] as u64) << 8)
+        self.load_bytes(addr, 2)
     }
     /// Reads a 32-bit word from memory.
     fn load_int(&self, addr: u64) -> u64 {
-        let index = (addr - MEMORY_START_ADDRESS) as usize;
-        self.int_load_util(index)
+        self.load_bytes(addr, 4)
     }
     /// Reads a 64-bit word from memory.
     fn load_long(&self, addr: u64) -> u64 {
-        let index = (addr - MEMORY_START_ADDRESS) as usize;
-        self.int_load_util(index)
-            | ((self.data[index + 4] as u64) << 32)
-            | ((self.data[index + 5] as u64) << 40)
-            | ((self.data[index + 6] as u64) << 48)
-            | ((self.data[index + 7] as u64) << 56)
+        self.load_bytes(addr, 8)
     }
-    fn int_load_util(&self, index: usize) -> u64 {
-        (self.data[index] as u64)
-            | ((self.data[index + 1] as u64) << 8)
-            | ((self.data[index + 2] as u64) << 16)
-            | ((self.data[index + 3] as u64) << 24)
+    /// Assembles `len` bytes starting at `addr` into a value in the configured byte order.
+    fn load_bytes(&self, addr: u64, len: usize) -> u64 {
+        let index = (addr - MEMORY_START_ADDRESS) as usize;
+        let bytes = (index..index + len).map(|i| self.byte(i) as u64);
+        match self.endianness {
+            Endianness::Little => bytes.rev().fold(0, |acc, byte| (acc << 8) | byte),
+            Endianness::Big => bytes.fold(0, |acc, byte| (acc << 8) | byte)
+        }
     }
 
     /// Writes a single byte to memory.
     fn write_byte(&mut self, addr: u64, val: u64) -> u64 {
-        let index = (addr - MEMORY_START_ADDRESS) as usize;
-        self.data[index] = val as u8;
-        index as u64
+        self.write_bytes(addr, val, 1)
     }
     /// Writes a 16-bit word to memory.
     fn write_short(&mut self, addr: u64, val: u64) -> u64 {
-        let index = (addr - MEMORY_START_ADDRESS) as usize;
-        self.data[index] = (val & 0xFF) as u8;
-        self.data[index+1] = ((val >> 8) & 0xFF) as u8;
-        index as u64
+        self.write_bytes(addr, val, 2)
     }
     /// Writes a 32-bit word to memory.
     fn write_int(&mut self, addr: u64, val: u64) -> u64 {
-        let index = (addr - MEMORY_START_ADDRESS) as usize;
-        self.data[index] = (val & 0xFF) as u8;
-        self.data[index + 1] = ((val >> 8) & 0xFF) as u8;
-        self.data[index + 2] = ((val >> 16) & 0xFF) as u8;
-        self.data[index + 3] = ((val >> 24) & 0xFF) as u8;
-        index as u64
+        self.write_bytes(addr, val, 4)
     }
     /// Writes a 64-bit word to memory.
     fn write_long(&mut self, addr: u64, val: u64) -> u64 {
+        self.write_bytes(addr, val, 8)
+    }
+    /// Splits the low `len` bytes of `val` into memory starting at `addr` in the configured byte order.
+    fn write_bytes(&mut self, addr: u64, val: u64, len: usize) -> u64 {
         let index = (addr - MEMORY_START_ADDRESS) as usize;
-        self.data[index] = (val & 0xFF) as u8;
-        self.data[index + 1] = ((val >> 8) & 0xFF) as u8;
-        self.data[index + 2] = ((val >> 16) & 0xFF) as u8;
-        self.data[index + 3] = ((val >> 24) & 0xFF) as u8;
-        self.data[index + 4] = ((val >> 32) & 0xFF) as u8;
-        self.data[index + 5] = ((val >> 40) & 0xFF) as u8;
-        self.data[index + 6] = ((val >> 48) & 0xFF) as u8;
-        self.data[index + 7] = ((val >> 56) & 0xFF) as u8;
+        for i in 0..len {
+            let shift = match self.endianness {
+                Endianness::Little => i * 8,
+                Endianness::Big => (len - 1 - i) * 8
+            };
+            self.set_byte(index + i, (val >> shift) as u8);
+        }
         index as u64
     }
 }
 impl AVMDevice for AVMMemory {
     fn write(&mut self, addr: u64, data: u64, size: usize) -> Result<u64, Exception> {
+        let index = self.check_access(addr, size)?;
+        self.mark_written(index, size / 8);
         match size {
             8 => {
                 Ok(self.write_byte(addr, data))
@@ -169,6 +445,7 @@ impl AVMDevice for AVMMemory {
         }
     }
     fn read(&mut self, addr: u64, size: usize) -> Result<u64, Exception> {
+        self.check_access(addr, size)?;
         match size {
             8 => {
                 Ok(self.load_byte(addr))
@@ -187,4 +464,156 @@ impl AVMDevice for AVMMemory {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct NullDevice;
+    impl AVMDevice for NullDevice {
+        fn write(&mut self, addr: u64, _data: u64, _size: usize) -> Result<u64, Exception> {
+            Ok(addr)
+        }
+        fn read(&mut self, _addr: u64, _size: usize) -> Result<u64, Exception> {
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn overlapping_device_ranges_are_rejected() {
+        let mut bus = AVMBus::new();
+        assert!(bus.map_device(0x80000000..0x80000010, Box::new(NullDevice)).is_ok());
+        assert_eq!(bus.map_overlap(&(0x80000008..0x80000018)), Some(0x80000000..0x80000010));
+        assert!(matches!(
+            bus.map_device(0x80000008..0x80000018, Box::new(NullDevice)),
+            Err(Exception::DeviceRangeConflict(0x80000008, 0x80000018))
+        ));
+        assert!(bus.map_device(0x80000010..0x80000020, Box::new(NullDevice)).is_ok());
+    }
+
+    struct RecordingDevice(Rc<RefCell<Vec<(u64, u64)>>>);
+    impl AVMDevice for RecordingDevice {
+        fn write(&mut self, addr: u64, data: u64, _size: usize) -> Result<u64, Exception> {
+            self.0.borrow_mut().push((addr, data));
+            Ok(addr)
+        }
+        fn read(&mut self, addr: u64, _size: usize) -> Result<u64, Exception> {
+            Ok(addr + 0x100)
+        }
+    }
+
+    #[test]
+    fn mapped_devices_receive_their_accesses() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = AVMBus::new();
+        bus.map_device(0x80000000..0x80000010, Box::new(RecordingDevice(log.clone()))).unwrap();
+        assert_eq!(bus.write(0x80000008, 0xAB, 8), Ok(8));
+        assert_eq!(bus.read(0x80000004, 32), Ok(0x104));
+        assert_eq!(*log.borrow(), vec![(8, 0xAB)]);
+        assert!(bus.memory().written_pages().is_empty());
+        assert_eq!(bus.read(0x80000010, 8), Err(Exception::AddressNotInMemoryBounds(0x80000010)));
+    }
+
+    #[test]
+    fn bus_bounds_are_exact_for_every_size() {
+        let mut bus = AVMBus::new();
+        let top = MEMORY_START_ADDRESS + MEMORY_SIZE;
+        for size in [8, 16, 32, 64] {
+            let last = top - size as u64 / 8;
+            assert!(bus.write(last, 1, size).is_ok(), "last {}-bit slot must be writable", size);
+            assert_eq!(bus.read(last, size), Ok(1));
+            assert_eq!(bus.read(top, size), Err(Exception::AddressNotInMemoryBounds(top)));
+            assert_eq!(bus.write(top, 1, size), Err(Exception::AddressNotInMemoryBounds(top)));
+            assert_eq!(
+                bus.read(MEMORY_START_ADDRESS - 8, size),
+                Err(Exception::AddressNotInMemoryBounds(MEMORY_START_ADDRESS - 8))
+            );
+        }
+        assert_eq!(bus.read(u64::MAX, 64), Err(Exception::AddressNotInMemoryBounds(u64::MAX)));
+    }
+
+    #[test]
+    fn multi_byte_values_follow_the_configured_byte_order() {
+        let mut memory = AVMMemory::new();
+        assert_eq!(memory.endianness(), Endianness::Little);
+        memory.write(MEMORY_START_ADDRESS, 0x0102, 16).unwrap();
+        assert_eq!(memory.read(MEMORY_START_ADDRESS, 8), Ok(0x02));
+        assert_eq!(memory.read(MEMORY_START_ADDRESS + 1, 8), Ok(0x01));
+
+        memory.set_endianness(Endianness::Big);
+        memory.write(MEMORY_START_ADDRESS, 0x0102, 16).unwrap();
+        assert_eq!(memory.read(MEMORY_START_ADDRESS, 8), Ok(0x01));
+        assert_eq!(memory.read(MEMORY_START_ADDRESS + 1, 8), Ok(0x02));
+        assert_eq!(memory.read(MEMORY_START_ADDRESS, 16), Ok(0x0102));
+
+        memory.write(MEMORY_START_ADDRESS + 8, 0x0102030405060708, 64).unwrap();
+        assert_eq!(memory.read(MEMORY_START_ADDRESS + 8, 8), Ok(0x01));
+        assert_eq!(memory.read(MEMORY_START_ADDRESS + 12, 32), Ok(0x05060708));
+    }
+
+    #[test]
+    fn sparse_memory_allocates_only_touched_pages() {
+        let mut memory = AVMMemory::sparse(MEMORY_SIZE);
+        let high = MEMORY_START_ADDRESS + MEMORY_SIZE - 8;
+        assert_eq!(memory.allocated_pages(), Some(0));
+        memory.write(high, 0x0102030405060708, 64).unwrap();
+        assert_eq!(memory.allocated_pages(), Some(1));
+        assert_eq!(memory.read(high, 64), Ok(0x0102030405060708));
+        assert_eq!(memory.read(high - 8, 64), Ok(0));
+        assert_eq!(memory.read(MEMORY_START_ADDRESS, 64), Ok(0));
+        assert_eq!(memory.allocated_pages(), Some(1));
+        assert_eq!(memory.read(high + 8, 64), Err(Exception::AddressNotInMemoryBounds(high + 8)));
+        assert_eq!(memory.written_pages().len(), 1);
+    }
+
+    #[test]
+    fn accesses_at_the_memory_ceiling_are_checked() {
+        let mut memory = AVMMemory::new();
+        let last_word = MEMORY_START_ADDRESS + MEMORY_SIZE - 8;
+        assert_eq!(memory.write(last_word, 0x0102030405060708, 64), Ok(MEMORY_SIZE - 8));
+        assert_eq!(memory.read(last_word, 64), Ok(0x0102030405060708));
+        assert_eq!(memory.read(last_word + 1, 64), Err(Exception::UnalignedAccess(last_word + 1)));
+        assert_eq!(memory.read(last_word + 8, 64), Err(Exception::AddressNotInMemoryBounds(last_word + 8)));
+        assert_eq!(memory.write(last_word + 7, 0xFF, 8), Ok(MEMORY_SIZE - 1));
+        assert_eq!(memory.read(last_word + 6, 16), Ok(0xFF02));
+    }
+    #[test]
+    fn clearing_zeroes_memory_in_place() {
+        let mut memory = AVMMemory::with_size(0x3000);
+        memory.set_endianness(Endianness::Big);
+        memory.write(MEMORY_START_ADDRESS + 0x1008, 0x0102030405060708, 64).unwrap();
+        let Storage::Dense(data) = &memory.storage else { panic!("expected dense memory") };
+        let allocation = data.as_ptr();
+        memory.clear();
+        let Storage::Dense(data) = &memory.storage else { panic!("expected dense memory") };
+        assert_eq!(data.as_ptr(), allocation);
+        assert!(data.iter().all(|&byte| byte == 0));
+        assert!(memory.written_pages().is_empty());
+        assert_eq!(memory.endianness(), Endianness::Big);
+
+        let mut memory = AVMMemory::sparse(MEMORY_SIZE);
+        memory.write(MEMORY_START_ADDRESS, 1, 64).unwrap();
+        memory.clear();
+        assert_eq!(memory.allocated_pages(), Some(0));
+        assert_eq!(memory.read(MEMORY_START_ADDRESS, 64), Ok(0));
+    }
+
+    #[test]
+    fn dumps_render_as_hex_and_ascii() {
+        let mut bus = AVMBus::new();
+        bus.write(MEMORY_START_ADDRESS, u64::from_le_bytes(*b"Hello, w"), 64).unwrap();
+        bus.write(MEMORY_START_ADDRESS + 8, u64::from_le_bytes(*b"orld!\n\0\x7F"), 64).unwrap();
+        let bytes = bus.dump(MEMORY_START_ADDRESS, 20);
+        assert_eq!(&bytes[..16], b"Hello, world!\n\0\x7F");
+        assert_eq!(&bytes[16..], &[0; 4]);
+        assert_eq!(hex_dump(MEMORY_START_ADDRESS, &bytes), "\
+10000000:  48 65 6C 6C 6F 2C 20 77  6F 72 6C 64 21 0A 00 7F  |Hello, world!...|
+10000010:  00 00 00 00                                       |....|
+");
+        assert_eq!(bus.dump(MEMORY_START_ADDRESS + MEMORY_SIZE - 2, 4).len(), 2);
+        assert!(bus.dump(0, 4).is_empty());
+    }
+}
@@ -1,3 +1,9 @@
 pub mod cpu;
+pub mod opcode;
+pub mod encode;
 pub mod memory;
-pub mod exceptions;
\ No newline at end of file
+pub mod exceptions;
+pub mod snapshot;
+pub mod disasm;
+pub mod console;
+pub mod loader;
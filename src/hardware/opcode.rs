@@ -0,0 +1,237 @@
+//! # AetherVM Opcodes
+//!
+//! This module names the operation field of an encoded instruction, i.e. bits 48..64. The [Opcode] enum is shared by
+//! [AVMCpu::execute_instr](crate::hardware::cpu::AVMCpu::execute_instr) and the disassembler, so both decode the same
+//! mapping. The encoded values come from the assembler's table in [bctranslator::encode], so the VM and the assembler
+//! cannot drift apart. Operand layouts are documented on `execute_instr`.
+//!
+use bctranslator::encode;
+use crate::hardware::exceptions::Exception;
+
+/// The operation of an instruction, with its encoded value as discriminant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u64)]
+pub enum Opcode {
+    /// `$nop`
+    Nop = encode::NOP,
+    /// `[regFrom] $move [regTo]`
+    Move = encode::MOVE,
+    /// `[reg] $set [value]`
+    Set = encode::SET,
+    /// `[reg1] $add [reg2] [resReg]`
+    Add = encode::ADD,
+    /// `[reg1] $sub [reg2] [resReg]`
+    Sub = encode::SUB,
+    /// `[reg1] $mul [reg2] [resReg]`
+    Mul = encode::MUL,
+    /// `[reg1] $div [reg2] [resReg]`
+    Div = encode::DIV,
+    /// `[address] $move [reg]`
+    LoadAbsolute = encode::LOAD_ABSOLUTE,
+    /// `[reg] $move [address]`
+    StoreAbsolute = encode::STORE_ABSOLUTE,
+    /// `[baseReg + disp] $move [reg]`
+    Load = encode::LOAD,
+    /// `[reg] $move [baseReg + disp]`
+    Store = encode::STORE,
+    /// `[reg1] $cmp [reg2]`
+    Cmp = encode::CMP,
+    /// `[destReg] $memcpy [srcReg] [lenReg]`
+    Memcpy = encode::MEMCPY,
+    /// `[reg1] $swap [reg2]`
+    Swap = encode::SWAP,
+    /// `[reg1] $sdiv [reg2] [resReg]`
+    Sdiv = encode::SDIV,
+    /// `[reg1] $srem [reg2] [resReg]`
+    Srem = encode::SREM,
+    /// `$inc [reg]`
+    Inc = encode::INC,
+    /// `$dec [reg]`
+    Dec = encode::DEC,
+    /// `[reg1] $addc [reg2] [resReg]`
+    Addc = encode::ADDC,
+    /// `[reg1] $mulc [reg2] [resReg]`
+    Mulc = encode::MULC,
+    /// `[reg] $lea [address]`
+    Lea = encode::LEA,
+    /// `[reg1] $cmov [cond] [reg2] [src] -> [dst]`
+    Cmov = encode::CMOV,
+    /// `[addr] $cas [expected] [new] -> [result]`
+    Cas = encode::CAS,
+    /// `[reg] $addm [baseReg + disp]`
+    Addm = encode::ADDM,
+    /// `[reg] $subm [baseReg + disp]`
+    Subm = encode::SUBM,
+    /// `[reg1] $adc [reg2] [resReg]`
+    Adc = encode::ADC,
+    /// `[srcReg] $neg [destReg]`
+    Neg = encode::NEG,
+    /// `[srcReg] $abs [destReg]`
+    Abs = encode::ABS,
+    /// `[reg1] $fadd [reg2] [resReg]`
+    Fadd = encode::FADD,
+    /// `[reg1] $fsub [reg2] [resReg]`
+    Fsub = encode::FSUB,
+    /// `[reg1] $fmul [reg2] [resReg]`
+    Fmul = encode::FMUL,
+    /// `[reg1] $fdiv [reg2] [resReg]`
+    Fdiv = encode::FDIV,
+    /// `[reg] $itof [resReg]`
+    Itof = encode::ITOF,
+    /// `[reg] $ftoi [resReg]`
+    Ftoi = encode::FTOI,
+    /// `$push [reg]`
+    Push = encode::PUSH,
+    /// `$pop [reg]`
+    Pop = encode::POP,
+    /// `[reg1] $and [reg2] [resReg]`
+    And = encode::AND,
+    /// `[reg1] $or [reg2] [resReg]`
+    Or = encode::OR,
+    /// `[reg1] $xor [reg2] [resReg]`
+    Xor = encode::XOR,
+    /// `[reg] $not [resReg]`
+    Not = encode::NOT,
+    /// `[reg] $shl [amountReg] [resReg]`
+    Shl = encode::SHL,
+    /// `[reg] $shr [amountReg] [resReg]`
+    Shr = encode::SHR,
+    /// `[reg] $sar [amountReg] [resReg]`
+    Sar = encode::SAR,
+    /// `[srcReg] $popcnt [destReg]`
+    Popcnt = encode::POPCNT,
+    /// `[srcReg] $clz [destReg]`
+    Clz = encode::CLZ,
+    /// `[srcReg] $ctz [destReg]`
+    Ctz = encode::CTZ,
+    /// `[srcReg] $bswap [destReg]`
+    Bswap = encode::BSWAP,
+    /// `$jmp [address]`
+    Jmp = encode::JMP,
+    /// `[reg1] $jmc [cond] [reg2]`
+    Jmc = encode::JMC,
+    /// `$call [address]`
+    Call = encode::CALL,
+    /// `$ret`
+    Ret = encode::RET,
+    /// `$jz [address]`
+    Jz = encode::JZ,
+    /// `$jnz [address]`
+    Jnz = encode::JNZ,
+    /// `$js [address]`
+    Js = encode::JS,
+    /// `$jns [address]`
+    Jns = encode::JNS,
+    /// `$jc [address]`
+    Jc = encode::JC,
+    /// `$jnc [address]`
+    Jnc = encode::JNC,
+    /// `$jo [address]`
+    Jo = encode::JO,
+    /// `$jno [address]`
+    Jno = encode::JNO,
+    /// `[reg] $jmci [cond] [imm]`
+    Jmci = encode::JMCI,
+    /// `$callr [reg]`
+    Callr = encode::CALLR,
+    /// `$fuel [resReg]`
+    Fuel = encode::FUEL,
+    /// `$syscall [number]`
+    Syscall = encode::SYSCALL,
+    /// `$exit [code]` / `$quit [code]`
+    Exit = encode::EXIT,
+    /// `$halt`
+    Halt = encode::HALT
+}
+impl TryFrom<u64> for Opcode {
+    type Error = Exception;
+
+    /// Decodes the operation field of an instruction, as extracted by `instr >> 48`.
+    ///
+    /// # Returns
+    /// - `Ok(Opcode)` for a known operation.
+    /// - `Err(Exception::UnexpectedOpcode)` otherwise.
+    fn try_from(operation: u64) -> Result<Opcode, Exception> {
+        match operation {
+            encode::NOP => Ok(Opcode::Nop),
+            encode::MOVE => Ok(Opcode::Move),
+            encode::SET => Ok(Opcode::Set),
+            encode::ADD => Ok(Opcode::Add),
+            encode::SUB => Ok(Opcode::Sub),
+            encode::MUL => Ok(Opcode::Mul),
+            encode::DIV => Ok(Opcode::Div),
+            encode::LOAD_ABSOLUTE => Ok(Opcode::LoadAbsolute),
+            encode::STORE_ABSOLUTE => Ok(Opcode::StoreAbsolute),
+            encode::LOAD => Ok(Opcode::Load),
+            encode::STORE => Ok(Opcode::Store),
+            encode::CMP => Ok(Opcode::Cmp),
+            encode::MEMCPY => Ok(Opcode::Memcpy),
+            encode::SWAP => Ok(Opcode::Swap),
+            encode::SDIV => Ok(Opcode::Sdiv),
+            encode::SREM => Ok(Opcode::Srem),
+            encode::INC => Ok(Opcode::Inc),
+            encode::DEC => Ok(Opcode::Dec),
+            encode::ADDC => Ok(Opcode::Addc),
+            encode::MULC => Ok(Opcode::Mulc),
+            encode::LEA => Ok(Opcode::Lea),
+            encode::CMOV => Ok(Opcode::Cmov),
+            encode::CAS => Ok(Opcode::Cas),
+            encode::ADDM => Ok(Opcode::Addm),
+            encode::SUBM => Ok(Opcode::Subm),
+            encode::ADC => Ok(Opcode::Adc),
+            encode::NEG => Ok(Opcode::Neg),
+            encode::ABS => Ok(Opcode::Abs),
+            encode::FADD => Ok(Opcode::Fadd),
+            encode::FSUB => Ok(Opcode::Fsub),
+            encode::FMUL => Ok(Opcode::Fmul),
+            encode::FDIV => Ok(Opcode::Fdiv),
+            encode::ITOF => Ok(Opcode::Itof),
+            encode::FTOI => Ok(Opcode::Ftoi),
+            encode::PUSH => Ok(Opcode::Push),
+            encode::POP => Ok(Opcode::Pop),
+            encode::AND => Ok(Opcode::And),
+            encode::OR => Ok(Opcode::Or),
+            encode::XOR => Ok(Opcode::Xor),
+            encode::NOT => Ok(Opcode::Not),
+            encode::SHL => Ok(Opcode::Shl),
+            encode::SHR => Ok(Opcode::Shr),
+            encode::SAR => Ok(Opcode::Sar),
+            encode::POPCNT => Ok(Opcode::Popcnt),
+            encode::CLZ => Ok(Opcode::Clz),
+            encode::CTZ => Ok(Opcode::Ctz),
+            encode::BSWAP => Ok(Opcode::Bswap),
+            encode::JMP => Ok(Opcode::Jmp),
+            encode::JMC => Ok(Opcode::Jmc),
+            encode::CALL => Ok(Opcode::Call),
+            encode::RET => Ok(Opcode::Ret),
+            encode::JZ => Ok(Opcode::Jz),
+            encode::JNZ => Ok(Opcode::Jnz),
+            encode::JS => Ok(Opcode::Js),
+            encode::JNS => Ok(Opcode::Jns),
+            encode::JC => Ok(Opcode::Jc),
+            encode::JNC => Ok(Opcode::Jnc),
+            encode::JO => Ok(Opcode::Jo),
+            encode::JNO => Ok(Opcode::Jno),
+            encode::JMCI => Ok(Opcode::Jmci),
+            encode::CALLR => Ok(Opcode::Callr),
+            encode::FUEL => Ok(Opcode::Fuel),
+            encode::SYSCALL => Ok(Opcode::Syscall),
+            encode::EXIT => Ok(Opcode::Exit),
+            encode::HALT => Ok(Opcode::Halt),
+            _ => Err(Exception::UnexpectedOpcode(operation))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operation_fields_decode_to_opcodes() {
+        assert_eq!(Opcode::try_from(0xCD02), Ok(Opcode::Add));
+        assert_eq!(Opcode::try_from(0xCF0C), Ok(Opcode::Jmci));
+        assert_eq!(Opcode::Halt as u64, 0xFFFF);
+        assert_eq!(Opcode::try_from(0x1234), Err(Exception::UnexpectedOpcode(0x1234)));
+    }
+}
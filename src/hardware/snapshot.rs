@@ -0,0 +1,104 @@
+//! # AetherVM Snapshot Module
+//!
+//! This module captures the complete execution state of an [AVMCpu](crate::hardware::cpu::AVMCpu) so that a program
+//! can be paused, written to disk, and resumed later, possibly in another process. A [CpuSnapshot] holds the registers,
+//! the instruction pointer, the status flags, the memory size and every memory page the program has written to. Devices mapped onto the
+//! bus are not part of a snapshot and have to be mapped again after restoring.
+//!
+//! The serialized form produced by [CpuSnapshot::to_bytes] is laid out as follows, with all integers little-endian:
+//! - The magic bytes `AVMS`.
+//! - 32 registers as `u64`.
+//! - The instruction pointer as `u64`.
+//! - The status flags as `u64`.
+//! - The memory size in bytes as `u64`.
+//! - The number of pages as `u64`, followed by each page as its `u64` offset, its `u64` length and that many bytes.
+//!   Pages are [PAGE_SIZE] bytes long, except for the last page of memory whose size is not a multiple of it.
+//!
+use crate::hardware::exceptions::Exception;
+use crate::hardware::memory::PAGE_SIZE;
+
+/// Magic bytes at the start of every serialized snapshot.
+const SNAPSHOT_MAGIC: &[u8; 4] = b"AVMS";
+
+/// A captured CPU and memory state that can be restored or serialized.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuSnapshot {
+    pub registers: [u64; 32],
+    pub ip: u64,
+    pub flags: u64,
+    /// The number of bytes of memory the snapshot was taken from.
+    pub memory_size: u64,
+    /// Written memory pages as `(offset, bytes)` pairs, with offsets relative to the start of memory.
+    pub pages: Vec<(u64, Vec<u8>)>
+}
+impl CpuSnapshot {
+    /// Serializes the snapshot into a byte buffer.
+    ///
+    /// # Returns
+    /// The serialized snapshot, readable by [CpuSnapshot::from_bytes].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + 8 * 36 + self.pages.len() * (16 + PAGE_SIZE));
+        bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        for register in self.registers {
+            bytes.extend_from_slice(&register.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.ip.to_le_bytes());
+        bytes.extend_from_slice(&self.flags.to_le_bytes());
+        bytes.extend_from_slice(&self.memory_size.to_le_bytes());
+        bytes.extend_from_slice(&(self.pages.len() as u64).to_le_bytes());
+        for (offset, page) in &self.pages {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&(page.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(page);
+        }
+        bytes
+    }
+    /// Deserializes a snapshot produced by [CpuSnapshot::to_bytes].
+    ///
+    /// # Returns
+    /// - `Ok(CpuSnapshot)` if the buffer holds a complete snapshot.
+    /// - `Err(Exception::MalformedSnapshot)` if the magic is wrong or the buffer is truncated.
+    pub fn from_bytes(bytes: &[u8]) -> Result<CpuSnapshot, Exception> {
+        let mut reader = SnapshotReader { bytes, pos: 0 };
+        if reader.take(4)? != SNAPSHOT_MAGIC {
+            return Err(Exception::MalformedSnapshot)
+        }
+        let mut registers = [0; 32];
+        for register in registers.iter_mut() {
+            *register = reader.read_u64()?;
+        }
+        let ip = reader.read_u64()?;
+        let flags = reader.read_u64()?;
+        let memory_size = reader.read_u64()?;
+        let count = reader.read_u64()?;
+        let mut pages = Vec::new();
+        for _ in 0..count {
+            let offset = reader.read_u64()?;
+            let len = reader.read_u64()?;
+            if len > PAGE_SIZE as u64 {
+                return Err(Exception::MalformedSnapshot)
+            }
+            pages.push((offset, reader.take(len as usize)?.to_vec()));
+        }
+        if reader.pos != bytes.len() {
+            return Err(Exception::MalformedSnapshot)
+        }
+        Ok(CpuSnapshot { registers, ip, flags, memory_size, pages })
+    }
+}
+
+struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+impl<'a> SnapshotReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Exception> {
+        let chunk = self.bytes.get(self.pos..self.pos + len).ok_or(Exception::MalformedSnapshot)?;
+        self.pos += len;
+        Ok(chunk)
+    }
+    fn read_u64(&mut self) -> Result<u64, Exception> {
+        let chunk = self.take(8)?;
+        Ok(u64::from_le_bytes(chunk.try_into().unwrap()))
+    }
+}
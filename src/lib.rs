@@ -0,0 +1,5 @@
+//! # AetherVM
+//!
+//! Library interface of the Aether Virtual Machine, allowing the CPU, memory bus and devices
+//! to be embedded in other programs.
+pub mod hardware;
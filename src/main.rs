@@ -1,19 +1,253 @@
-use std::env;
-use std::path::Path;
+use std::{env, fs};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
 use colored::{Colorize};
-
-mod hardware;
+use aethervm::hardware::console::{ConsoleDevice, CONSOLE_RANGE};
+use aethervm::hardware::cpu::{AVMCpu, StepOutcome};
+use aethervm::hardware::disasm::{disassemble, render_program_colored};
+use aethervm::hardware::loader::{load_program, program_sections};
+use aethervm::hardware::memory::{hex_dump, MEMORY_START_ADDRESS};
+use bctranslator::TranslateError;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() == 1 {
-        println!("{}\n{} {}\n\n{}\n{}\t\t{}", "Aether Virtual Machine", "Usage:".green(), "aethervm [COMMAND]".truecolor(0, 255, 255), "Options:".green(), "run".truecolor(0, 255, 255), "Execute and run program from bytecode file")
-    }else {
-        let arg = &args[1];
-        let path = Path::new(&args[2]);
-        println!("Arg is {}, arg value is {}", arg, path.display());
+        print_usage()
+    } else if args[1] == "run" {
+        run(&args[2..])
+    } else if args[1] == "disas" || args[1] == "disasm" {
+        disas(&args[2..])
+    } else if args[1] == "asm" {
+        asm(&args[2..])
+    } else if args[1] == "debug" {
+        debug(&args[2..])
+    } else {
+        eprintln!("{} unknown command {}", "error:".red(), args[1]);
+        print_usage();
+        exit(1)
+    }
+}
+
+fn print_usage() {
+    println!("{}\n{} {}\n\n{}\n{}\t\t{}\n{}\t\t{}\n{}\t\t{}\n{}\t\t{}", "Aether Virtual Machine", "Usage:".green(), "aethervm [COMMAND]".truecolor(0, 255, 255), "Options:".green(), "run".truecolor(0, 255, 255), "Execute and run program from bytecode file", "disas".truecolor(0, 255, 255), "Print bytecode file as mnemonics (--no-color for plain output)", "asm".truecolor(0, 255, 255), "Translate source file into bytecode file (-o to choose the output path)", "debug".truecolor(0, 255, 255), "Step through bytecode file interactively (step, continue, regs, mem, break, quit)")
+}
+
+/// Returns the first non-flag argument of a subcommand.
+///
+/// Prints an error and the usage and exits with a non-zero code if the argument is missing.
+fn path_arg<'a>(command: &str, args: &'a [String]) -> &'a str {
+    match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(path) => path,
+        None => {
+            eprintln!("{} {} expects a bytecode file", "error:".red(), command);
+            print_usage();
+            exit(1)
+        }
+    }
+}
+
+/// Loads a bytecode image into a fresh CPU, runs it to completion and exits with the program's exit code.
+///
+/// The console device is mapped at [CONSOLE_RANGE] so programs can print.
+fn run(args: &[String]) {
+    let path = path_arg("run", args);
+    let mut cpu = AVMCpu::new();
+    let result = load_program(Path::new(path), &mut cpu)
+        .and_then(|_| cpu.bus_mut().map_device(CONSOLE_RANGE, Box::new(ConsoleDevice::stdout())))
+        .and_then(|_| cpu.run());
+    match result {
+        Ok(code) => exit(code),
+        Err(e) => {
+            eprintln!("{} {}", "error:".red(), e);
+            exit(1)
+        }
     }
-    // println!("{:X}", 0x124567890102u64 & 0x000FFFFFFFFF)
+}
 
+/// Prints the instructions of a bytecode image as an addressed disassembly listing.
+///
+/// The image header is checked like [load_program] does. Only the code is listed, not the data section after it. Words
+/// with an unknown opcode are listed as `.word`.
+/// Colors are disabled by `--no-color` or the `NO_COLOR` environment variable. `disasm` is accepted as an alias.
+fn disas(args: &[String]) {
+    if args.iter().any(|arg| arg == "--no-color") {
+        colored::control::set_override(false)
+    }
+    let path = path_arg("disas", args);
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{} cannot read {}: {}", "error:".red(), path, e);
+            exit(1)
+        }
+    };
+    let code = match program_sections(&bytes) {
+        Ok((code, _)) => code,
+        Err(e) => {
+            eprintln!("{} {}: {}", "error:".red(), path, e);
+            exit(1)
+        }
+    };
+    let words: Vec<u64> = code.chunks(8)
+        .map(|chunk| {
+            let mut word = [0u8; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u64::from_le_bytes(word)
+        })
+        .collect();
+    print!("{}", render_program_colored(&words, MEMORY_START_ADDRESS));
+}
+
+/// Translates a source file into a bytecode image.
+///
+/// The image is written to the path following `-o`, or next to the source with the `.avm` extension.
+/// Lexer, parser and codegen errors are reported with their position and exit with a non-zero code. All syntax errors
+/// are reported, not only the first, each followed by its source line with the offending token underlined.
+fn asm(args: &[String]) {
+    let mut output = None;
+    let mut input = None;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        if arg == "-o" {
+            output = rest.next().map(PathBuf::from)
+        } else if input.is_none() {
+            input = Some(arg.as_str())
+        }
+    }
+    let Some(input) = input else {
+        eprintln!("{} asm expects a source file", "error:".red());
+        print_usage();
+        exit(1)
+    };
+    let output = output.unwrap_or_else(|| Path::new(input).with_extension("avm"));
+    let source = match fs::read_to_string(input) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{} cannot read {}: {}", "error:".red(), input, e);
+            exit(1)
+        }
+    };
+    if let Err(errors) = bctranslator::check(&source) {
+        for e in errors {
+            eprintln!("{} {}: {}", "error:".red(), input, e);
+            if let TranslateError::Parse(e) = e {
+                eprintln!("{}", underline(&source, e.span()));
+            }
+        }
+        exit(1)
+    }
+    let bytecode = match bctranslator::translate(&source) {
+        Ok(bytecode) => bytecode,
+        Err(e) => {
+            eprintln!("{} {}: {}", "error:".red(), input, e);
+            exit(1)
+        }
+    };
+    if let Err(e) = bctranslator::write_bytecode(&output, &bytecode) {
+        eprintln!("{} cannot write {}: {}", "error:".red(), output.display(), e);
+        exit(1)
+    }
+}
+
+/// Renders the source line containing the character range `start..end`, with the range marked by `^` below it.
+fn underline(source: &str, (start, end): (usize, usize)) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let start = start.min(chars.len());
+    let line_start = chars[..start].iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+    let line_end = chars[start..].iter().position(|&c| c == '\n').map_or(chars.len(), |i| start + i);
+    let line: String = chars[line_start..line_end].iter().collect();
+    // Keep tabs so the marker lines up with the line above it
+    let indent: String = chars[line_start..start].iter().map(|&c| if c == '\t' { '\t' } else { ' ' }).collect();
+    format!("  {}\n  {}{}", line, indent, "^".repeat(end.min(line_end).saturating_sub(start).max(1)))
+}
+
+/// Loads a bytecode image like `run` does and reads debugger commands from stdin, one per line:
+/// - `step`: executes one instruction and prints it.
+/// - `continue`: runs until a breakpoint is reached or the program stops.
+/// - `regs`: prints the registers, the instruction pointer and the flags.
+/// - `mem <addr>`: prints a hex dump of the 64 bytes from a hexadecimal address.
+/// - `break <addr>`: stops `continue` before the instruction at a hexadecimal address.
+/// - `quit`: leaves the debugger, as does the end of input.
+///
+/// When the program stops the debugger exits with its exit code; faults exit with a non-zero code.
+fn debug(args: &[String]) {
+    let path = path_arg("debug", args);
+    let mut cpu = AVMCpu::new();
+    let result = load_program(Path::new(path), &mut cpu)
+        .and_then(|_| cpu.bus_mut().map_device(CONSOLE_RANGE, Box::new(ConsoleDevice::stdout())));
+    if let Err(e) = result {
+        eprintln!("{} {}", "error:".red(), e);
+        exit(1)
+    }
+    let mut breakpoints = BTreeSet::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(avm) ");
+        io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else {
+            println!();
+            return
+        };
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next().map(parse_address)) {
+            (None, _) => {}
+            (Some("step" | "s"), _) => debug_step(&mut cpu, true),
+            (Some("continue" | "c"), _) => loop {
+                debug_step(&mut cpu, false);
+                if breakpoints.contains(&cpu.ip()) {
+                    println!("breakpoint at {:X}", cpu.ip());
+                    break
+                }
+            },
+            (Some("regs" | "r"), _) => {
+                for (i, row) in cpu.registers().chunks(4).enumerate() {
+                    let row: Vec<String> = row.iter().enumerate().map(|(j, value)| format!("r{:<2} = {:016X}", i * 4 + j, value)).collect();
+                    println!("{}", row.join("  "));
+                }
+                println!("ip  = {:016X}  flags = {:04b}", cpu.ip(), cpu.flags());
+            }
+            (Some("mem" | "m"), Some(Some(address))) => match cpu.bus_mut().dump(address, 64) {
+                bytes if bytes.is_empty() => eprintln!("{} {:X} cannot be read", "error:".red(), address),
+                bytes => print!("{}", hex_dump(address, &bytes))
+            },
+            (Some("break" | "b"), Some(Some(address))) => {
+                breakpoints.insert(address);
+                println!("breakpoint set at {:X}", address);
+            }
+            (Some("mem" | "m" | "break" | "b"), _) => eprintln!("{} expected a hexadecimal address", "error:".red()),
+            (Some("quit" | "q"), _) => return,
+            (Some(command), _) => eprintln!("{} unknown debugger command {}", "error:".red(), command)
+        }
+    }
+}
+
+/// Executes one instruction for the debugger, optionally printing it first.
+///
+/// Exits the process with the program's exit code if it stopped, or with 1 if the instruction faulted.
+fn debug_step(cpu: &mut AVMCpu, print: bool) {
+    let ip = cpu.ip();
+    if print {
+        if let Ok(instr) = cpu.load_instr() {
+            println!("{:08X}:  {}", ip, disassemble(instr));
+        }
+    }
+    match cpu.step() {
+        Ok(StepOutcome::Running) => {}
+        Ok(StepOutcome::Halted(code)) => {
+            println!("program exited with code {}", code);
+            exit(code)
+        }
+        Err(e) => {
+            eprintln!("{} {}", "error:".red(), e);
+            exit(1)
+        }
+    }
+}
 
+/// Parses a hexadecimal address, with or without a `0x` prefix.
+fn parse_address(text: &str) -> Option<u64> {
+    u64::from_str_radix(text.trim_start_matches("0x"), 16).ok()
 }
@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::PathBuf;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use aethervm::hardware::encode::{encode_add, encode_bare, encode_exit, encode_set};
+use aethervm::hardware::opcode::Opcode;
+
+fn write_image(name: &str, program: &[u64]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("aethervm-{}-{}.avm", name, std::process::id()));
+    let mut bytes = b"AVM\0\x02".to_vec();
+    bytes.extend_from_slice(&(program.len() as u64 * 8).to_le_bytes());
+    bytes.extend(program.iter().flat_map(|instr| instr.to_le_bytes()));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn aethervm(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_aethervm"))
+        .args(args)
+        .env_remove("NO_COLOR")
+        .env("CLICOLOR_FORCE", "1")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn disasm_colors_output_unless_disabled() {
+    let path = write_image("disasm-color", &[encode_add(1, 2, 3), encode_bare(Opcode::Halt)]);
+    let path = path.to_str().unwrap();
+
+    let plain = aethervm(&["disasm", "--no-color", path]);
+    assert!(plain.status.success());
+    assert_eq!(String::from_utf8(plain.stdout).unwrap(), "\
+10000000:  CD02010203000000  r1 $add r2 -> r3
+10000008:  FFFF000000000000  $halt
+");
+
+    let colored = aethervm(&["disas", path]);
+    assert!(colored.status.success());
+    assert!(String::from_utf8(colored.stdout).unwrap().contains("\x1b["));
+}
+
+#[test]
+fn disas_lists_a_program_and_checks_its_header() {
+    let path = write_image("disas", &[encode_set(1, 0x2A), 0x1234_0000_0000_0000, encode_exit(3)]);
+    let output = aethervm(&["disas", "--no-color", path.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines, [
+        "10000000:  CD0101000000002A  r1 $set 0x2A",
+        "10000008:  1234000000000000  .word 0x1234000000000000",
+        "10000010:  FFFA030000000000  $exit 3"
+    ]);
+
+    let path = std::env::temp_dir().join(format!("aethervm-disas-raw-{}.avm", std::process::id()));
+    fs::write(&path, encode_bare(Opcode::Halt).to_le_bytes()).unwrap();
+    let output = aethervm(&["disas", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("valid AVM header"));
+}
+
+#[test]
+fn run_exits_with_the_program_exit_code() {
+    let path = write_image("run-exit", &[encode_set(1, 7), encode_exit(42)]);
+    let output = aethervm(&["run", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(42));
+
+    let path = write_image("run-halt", &[encode_bare(Opcode::Nop), encode_bare(Opcode::Halt)]);
+    assert_eq!(aethervm(&["run", path.to_str().unwrap()]).status.code(), Some(0));
+
+    let output = aethervm(&["run", "/nonexistent/program.avm"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("could not be read"));
+}
+
+#[test]
+fn subcommands_without_a_path_fail_cleanly() {
+    for (command, input) in [("run", "bytecode"), ("disas", "bytecode"), ("debug", "bytecode"), ("asm", "source")] {
+        let output = aethervm(&[command]);
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(&format!("{} expects a {} file", command, input)));
+        assert!(!stderr.contains("panicked"));
+        assert!(String::from_utf8(output.stdout).unwrap().contains("Usage:"));
+    }
+}
+
+#[test]
+fn asm_output_runs_in_the_vm() {
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("aethervm-asm-{}.avms", std::process::id()));
+    let image = dir.join(format!("aethervm-asm-{}.avm", std::process::id()));
+    fs::write(&source, "$funcdef main {\n\t$set r1, 7\n\t$exit 42\n}\n").unwrap();
+
+    let output = aethervm(&["asm", source.to_str().unwrap(), "-o", image.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(aethervm(&["run", image.to_str().unwrap()]).status.code(), Some(42));
+
+    fs::write(&source, "$funcdef main {\n\t$set r1, ?\n}\n").unwrap();
+    let output = aethervm(&["asm", source.to_str().unwrap(), "-o", image.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("at pos"));
+
+    fs::write(&source, "$funcdef main {\n\t$jmc r1, r2, main\n\t$vardef = 1\n}\n").unwrap();
+    let output = aethervm(&["asm", source.to_str().unwrap(), "-o", image.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.matches("Unexpected").count(), 2);
+    assert!(stderr.contains("  \t$jmc r1, r2, main\n  \t     ^^\n"));
+}
+
+#[test]
+fn asm_data_is_writable_and_not_code() {
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("aethervm-data-{}.avms", std::process::id()));
+    let image = dir.join(format!("aethervm-data-{}.avm", std::process::id()));
+    fs::write(&source, "$data msg, \"Hello, world!\", 0\n$funcdef main {\n\t$lea r1, msg\n\t$set r2, 72\n\
+                        \t$subm r2, 8, r1, 0\n\t$exit 42\n}\n").unwrap();
+    let output = aethervm(&["asm", source.to_str().unwrap(), "-o", image.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(aethervm(&["run", image.to_str().unwrap()]).status.code(), Some(42));
+
+    let output = aethervm(&["disas", "--no-color", image.to_str().unwrap()]);
+    assert!(!String::from_utf8(output.stdout).unwrap().contains(".word"));
+}
+
+#[test]
+fn called_functions_read_their_parameters() {
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("aethervm-params-{}.avms", std::process::id()));
+    let image = dir.join(format!("aethervm-params-{}.avm", std::process::id()));
+    let programs = [
+        "$funcdef main {\n\t$call check (5, 7)\n\t$exit 1\n}\n\
+         $funcdef check $params a, b {\n\t$vardef sum = a + b\n\t$jmci eq r2, 12, good\n\t$exit 2\ngood:\n\t$exit 42\n}\n",
+        "$funcdef main {\n\t$call check (1, 2, 3, 4, 5, 6)\n\t$exit 1\n}\n\
+         $funcdef check $params a, b, c, d, e, f {\n\t$vardef diff = f - e\n\t$jmci eq r6, 1, good\n\t$exit 2\ngood:\n\t$exit 42\n}\n",
+    ];
+    for program in programs {
+        fs::write(&source, program).unwrap();
+        let output = aethervm(&["asm", source.to_str().unwrap(), "-o", image.to_str().unwrap()]);
+        assert!(output.status.success());
+        assert_eq!(aethervm(&["run", image.to_str().unwrap()]).status.code(), Some(42));
+    }
+}
+
+#[test]
+fn callers_read_the_returned_value_from_r0() {
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("aethervm-ret-{}.avms", std::process::id()));
+    let image = dir.join(format!("aethervm-ret-{}.avm", std::process::id()));
+    fs::write(&source, "$funcdef main {\n\t$call five (9, 9, 9, 9, 9)\n\t$jmci eq r0, 5, good\n\t$exit 1\ngood:\n\t$exit 42\n}\n\
+                        $funcdef five $params a, b, c, d, e {\n\t$ret 2 + 3\n}\n").unwrap();
+    let output = aethervm(&["asm", source.to_str().unwrap(), "-o", image.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(aethervm(&["run", image.to_str().unwrap()]).status.code(), Some(42));
+}
+
+#[test]
+fn caller_variables_survive_a_call() {
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("aethervm-spill-{}.avms", std::process::id()));
+    let image = dir.join(format!("aethervm-spill-{}.avm", std::process::id()));
+    let programs = [
+        "$funcdef main {\n\t$vardef x = 7\n\t$call f (1)\n\t$vardef y = x\n\t$jmci eq r0, 7, good\n\t$exit 1\ngood:\n\t$exit 42\n}\n\
+         $funcdef f $params a {\n\t$vardef junk = a + 100\n\t$vardef more = junk + 1\n\t$ret\n}\n",
+        "$funcdef main {\n\t$vardef x = 7\n\t$call f (1)\n\t$jmci eq r0, 8, good\n\t$exit 1\ngood:\n\t$vardef y = x\n\t$jmci eq r0, 7, done\n\t$exit 2\ndone:\n\t$exit 42\n}\n\
+         $funcdef f $params a {\n\t$ret a + 7\n}\n",
+    ];
+    for program in programs {
+        fs::write(&source, program).unwrap();
+        let output = aethervm(&["asm", source.to_str().unwrap(), "-o", image.to_str().unwrap()]);
+        assert!(output.status.success());
+        assert_eq!(aethervm(&["run", image.to_str().unwrap()]).status.code(), Some(42));
+    }
+}
+
+#[test]
+fn debug_steps_and_continues_from_a_script() {
+    let path = write_image("debug", &[encode_set(1, 7), encode_set(2, 9), encode_bare(Opcode::Nop), encode_exit(5)]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_aethervm"))
+        .args(["debug", path.to_str().unwrap()])
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"step\nregs\nbreak 10000010\nmem 10000000\ncontinue\ncontinue\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert!(stdout.contains("10000000:  r1 $set 0x7"));
+    assert!(stdout.contains("r1  = 0000000000000007"));
+    assert!(stdout.contains("ip  = 0000000010000008"));
+    assert!(stdout.contains("10000000:  07 00 00 00 00 01 01 CD  09 00 00 00 00 02 01 CD  |................|"));
+    assert!(stdout.contains("breakpoint at 10000010"));
+    assert!(stdout.contains("program exited with code 5"));
+}